@@ -0,0 +1,91 @@
+//! Compares `Query::run` with a cached `QueryState` on 100k entities
+//! of which only a small fraction match.
+//! Run with `cargo bench --bench query_state`.
+//!
+//! On a single core with rustc 1.95, 1000 of 100k entities matching:
+//!
+//! ```text
+//! Query::run         ~175µs per run
+//! QueryState::run    ~25µs per run
+//! Query::count       ~60-75µs per run
+//! QueryState::count  <0.1µs per run
+//! ```
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use ecs_library::World;
+
+const ENTITIES: usize = 100_000;
+const ITERATIONS: u32 = 50;
+
+struct Location(pub f32, pub f32);
+struct Velocity(pub f32, pub f32);
+
+fn initialize_world() -> anyhow::Result<World> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Velocity>();
+    for index in 0..ENTITIES {
+        let entity = world.create_entity()
+            .with_component(Location(index as f32, 0.0))?;
+        if index % 100 == 0 {
+            entity.with_component(Velocity(1.0, 1.0))?;
+        }
+    }
+    Ok(world)
+}
+
+fn time(iterations: u32, mut run: impl FnMut() -> usize) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(run());
+    }
+    start.elapsed() / iterations
+}
+
+fn main() -> anyhow::Result<()> {
+    let world = initialize_world()?;
+
+    let query_run = time(ITERATIONS, || {
+        world.query()
+            .with_component::<Location>().unwrap()
+            .with_component::<Velocity>().unwrap()
+            .run().0.len()
+    });
+
+    let mut state = world.query()
+        .with_component::<Location>()?
+        .with_component::<Velocity>()?
+        .state();
+    let state_run = time(ITERATIONS, || world.run_query_state(&mut state).0.len());
+
+    let query_count = time(ITERATIONS, || {
+        world.query()
+            .with_component::<Location>().unwrap()
+            .with_component::<Velocity>().unwrap()
+            .count()
+    });
+    let state_count = time(ITERATIONS, || world.count_query_state(&mut state));
+
+    let (indexes, components) = world.run_query_state(&mut state);
+    let moved = components[0]
+        .iter()
+        .zip(&components[1])
+        .map(|(location, velocity)| {
+            let location = location.borrow();
+            let location = location.downcast_ref::<Location>().unwrap();
+            let velocity = velocity.borrow();
+            let velocity = velocity.downcast_ref::<Velocity>().unwrap();
+            location.0 + location.1 + velocity.0 + velocity.1
+        })
+        .sum::<f32>();
+
+    println!("{ENTITIES} entities, {} matching (checksum {moved})", indexes.len());
+    println!("Query::run         {query_run:?} per run");
+    println!("QueryState::run    {state_run:?} per run");
+    println!("Query::count       {query_count:?} per run");
+    println!("QueryState::count  {state_count:?} per run");
+
+    Ok(())
+}
@@ -0,0 +1,220 @@
+//! Shared data such as meshes or sounds. An `Assets<T>` store lives in the
+//! world as a resource and hands out `Handle<T>`s, which entities keep as
+//! components. Once the last handle to an asset is dropped, for example because
+//! the components holding it were deleted, the next `update` unloads the asset.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use crate::{Entity, World};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetId(u64);
+
+/// Reports its asset to the store when the last handle to it goes away.
+struct HandleInner {
+    id: AssetId,
+    dropped: Rc<RefCell<Vec<AssetId>>>,
+}
+
+impl Drop for HandleInner {
+    fn drop(&mut self) {
+        self.dropped.borrow_mut().push(self.id);
+    }
+}
+
+/// Reference counted pointer to an asset of a store. Cloning only bumps the count.
+pub struct Handle<T> {
+    inner: Rc<HandleInner>,
+    kind: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn id(&self) -> AssetId {
+        self.inner.id
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone(), kind: PhantomData }
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Handle").field(&self.inner.id).finish()
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetEvent {
+    Added(AssetId),
+    /// Handed out through `get_mut`.
+    Modified(AssetId),
+    /// The last handle was dropped and the asset is gone.
+    Unloaded(AssetId),
+}
+
+pub struct Assets<T> {
+    assets: HashMap<AssetId, T>,
+    next_id: u64,
+    dropped: Rc<RefCell<Vec<AssetId>>>,
+    events: Vec<AssetEvent>,
+}
+
+impl<T> Default for Assets<T> {
+    fn default() -> Self {
+        Self {
+            assets: HashMap::new(),
+            next_id: 0,
+            dropped: Rc::new(RefCell::new(vec![])),
+            events: vec![],
+        }
+    }
+}
+
+impl<T> fmt::Debug for Assets<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Assets")
+            .field("loaded", &self.assets.len())
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+impl<T> Assets<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, asset: T) -> Handle<T> {
+        let id = AssetId(self.next_id);
+        self.next_id += 1;
+        self.assets.insert(id, asset);
+        self.events.push(AssetEvent::Added(id));
+        Handle {
+            inner: Rc::new(HandleInner { id, dropped: self.dropped.clone() }),
+            kind: PhantomData,
+        }
+    }
+
+    pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+        self.assets.get(&handle.id())
+    }
+
+    pub fn get_mut(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        let asset = self.assets.get_mut(&handle.id())?;
+        self.events.push(AssetEvent::Modified(handle.id()));
+        Some(asset)
+    }
+
+    /// Loaded assets, including ones whose handles are gone but that `update` has not unloaded yet.
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Unloads every asset that no handle points at anymore. Returns how many were unloaded.
+    pub fn update(&mut self) -> usize {
+        let dropped = std::mem::take(&mut *self.dropped.borrow_mut());
+        for id in &dropped {
+            self.assets.remove(id);
+            self.events.push(AssetEvent::Unloaded(*id));
+        }
+        dropped.len()
+    }
+
+    /// Events since the last call, oldest first. Runs `update` first.
+    pub fn drain_events(&mut self) -> Vec<AssetEvent> {
+        self.update();
+        std::mem::take(&mut self.events)
+    }
+}
+
+impl World {
+    /// Adds an empty `Assets<T>` resource, unless there is one, and registers `Handle<T>`.
+    pub fn add_assets<T: Any>(&mut self) {
+        if self.get_resource::<Assets<T>>().is_none() {
+            self.add_resouce(Assets::<T>::new());
+        }
+        self.register_clone::<Handle<T>>();
+    }
+
+    pub fn assets<T: Any>(&self) -> Option<&Assets<T>> {
+        self.get_resource::<Assets<T>>()
+    }
+
+    pub fn assets_mut<T: Any>(&mut self) -> Option<&mut Assets<T>> {
+        self.get_resource_mut::<Assets<T>>()
+    }
+
+    /// Entities holding a handle to the asset, e.g. to react to an `AssetEvent`.
+    pub fn entities_with_asset<T: Any>(&self, id: AssetId) -> Vec<Entity> {
+        let mut query = self.query();
+        if query.with_component::<Handle<T>>().is_err() {
+            return vec![];
+        }
+        query.run_entities()
+            .into_iter()
+            .filter(|(_entity, components)| {
+                components[0].borrow().downcast_ref::<Handle<T>>().is_some_and(|handle| handle.id() == id)
+            })
+            .map(|(entity, _components)| entity)
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn assets_unload_with_their_last_handle() {
+        let mut meshes = Assets::<Vec<f32>>::new();
+        let handle = meshes.add(vec![0.0, 1.0]);
+        let copy = handle.clone();
+        let other = meshes.add(vec![]);
+
+        drop(handle);
+        assert_eq!(meshes.update(), 0);
+        meshes.get_mut(&copy).unwrap().push(2.0);
+        assert_eq!(meshes.get(&copy).unwrap().len(), 3);
+
+        drop(copy);
+        assert_eq!(meshes.len(), 2);
+        let events = meshes.drain_events();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(events, vec![
+            AssetEvent::Added(AssetId(0)),
+            AssetEvent::Added(other.id()),
+            AssetEvent::Modified(AssetId(0)),
+            AssetEvent::Unloaded(AssetId(0)),
+        ]);
+        assert!(meshes.drain_events().is_empty());
+    }
+}
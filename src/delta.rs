@@ -0,0 +1,202 @@
+//! Differences between two worlds, for sending state over the network or for undo.
+//! `World::diff` compares entities slot by slot and the resources registered with
+//! `register_diff_resource`; `World::apply` replays the result on a world that
+//! matches the one it was computed from. Deltas that only hold components and
+//! resources with a codec can be turned into bytes with `encode_delta`.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+use crate::entity::delta::{read_name, write_name, EntitesDelta};
+use crate::entity::snapshot::Reader;
+use crate::{BinaryComponent, Error, Result, World};
+
+
+const MAGIC: &[u8; 4] = b"ECSD";
+pub const DELTA_VERSION: u16 = 1;
+
+/// How a resource is written to and read from bytes.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ResourceCodec {
+    name: &'static str,
+    encode: fn(&dyn Any, &mut Vec<u8>),
+    decode: fn(&[u8]) -> Option<Box<dyn Any>>,
+}
+
+pub(crate) type ResourceCodecs = HashMap<TypeId, ResourceCodec>;
+
+impl ResourceCodec {
+    fn of<T: BinaryComponent>() -> Self {
+        Self { name: std::any::type_name::<T>(), encode: encode_resource::<T>, decode: decode_resource::<T> }
+    }
+}
+
+fn encode_resource<T: BinaryComponent>(data: &dyn Any, buf: &mut Vec<u8>) {
+    if let Some(data) = data.downcast_ref::<T>() {
+        data.encode(buf);
+    }
+}
+
+fn decode_resource<T: BinaryComponent>(bytes: &[u8]) -> Option<Box<dyn Any>> {
+    Some(Box::new(T::decode(bytes)?))
+}
+
+/// Result of `World::diff`. Resources are kept encoded, `None` when the resource was removed.
+#[derive(Debug, Clone, Default)]
+pub struct WorldDelta {
+    entities: EntitesDelta,
+    resources: Vec<(TypeId, Option<Vec<u8>>)>,
+}
+
+impl WorldDelta {
+    pub fn entities(&self) -> &EntitesDelta {
+        &self.entities
+    }
+
+    /// Resources that were added, changed or removed.
+    pub fn changed_resources(&self) -> Vec<TypeId> {
+        self.resources.iter().map(|(type_id, _bytes)| *type_id).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty() && self.resources.is_empty()
+    }
+}
+
+impl World {
+    /// Lets `diff` compare `T` components that have no codec.
+    /// Deltas holding them cannot be encoded.
+    pub fn register_diff<T: Any + Clone + PartialEq>(&mut self) {
+        self.entities.register_diff::<T>();
+        self.registry.register::<T>();
+    }
+
+    /// Makes `diff` compare the resource `T` by its encoded bytes.
+    pub fn register_diff_resource<T: BinaryComponent>(&mut self) {
+        self.resource_codecs.insert(TypeId::of::<T>(), ResourceCodec::of::<T>());
+    }
+
+    /// What `apply` needs to turn this world into `other`. Only components with a codec,
+    /// tags and components registered with `register_diff` are compared.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut server = World::new();
+    /// server.register_binary_component::<u32>();
+    /// server.create_entity().with_component(1_u32).unwrap();
+    /// let mut client = World::new();
+    /// client.register_binary_component::<u32>();
+    ///
+    /// let delta = client.diff(&server).unwrap();
+    /// let bytes = server.encode_delta(&delta).unwrap();
+    /// client.apply(&client.decode_delta(&bytes).unwrap()).unwrap();
+    /// assert_eq!(*client.get::<u32>(0).unwrap(), 1);
+    /// ```
+    pub fn diff(&self, other: &World) -> Result<WorldDelta> {
+        let mut resources = vec![];
+        let mut codecs = self.resource_codecs.iter().collect::<Vec<_>>();
+        codecs.sort_by_key(|(_type_id, codec)| codec.name);
+        for (type_id, codec) in codecs {
+            let before = encode_resource_of(self, type_id, codec);
+            let after = encode_resource_of(other, type_id, codec);
+            if before != after {
+                resources.push((*type_id, after));
+            }
+        }
+        Ok(WorldDelta { entities: self.entities.diff(&other.entities)?, resources })
+    }
+
+    /// Replays a delta from `diff`. Fails without changing anything when it does not fit this world.
+    pub fn apply(&mut self, delta: &WorldDelta) -> Result<()> {
+        let mut resources = vec![];
+        for (type_id, bytes) in &delta.resources {
+            let codec = self.resource_codecs.get(type_id).ok_or_else(
+                || Error::UnknownSnapshotComponent("delta holds a resource without a codec".to_string())
+            )?;
+            let data = match bytes {
+                Some(bytes) => Some((codec.decode)(bytes).ok_or_else(
+                    || Error::InvalidSnapshot(format!("could not decode {}", codec.name))
+                )?),
+                None => None,
+            };
+            resources.push((*type_id, data));
+        }
+
+        self.entities.apply(&delta.entities)?;
+        for (type_id, data) in resources {
+            match data {
+                Some(data) => self.resources.insert_by_id(type_id, data),
+                None => self.resources.remove_by_id(&type_id),
+            }
+        }
+        Ok(())
+    }
+
+    /// Layout: magic, version, entity changes, then the resources by name
+    /// with a presence byte and their length prefixed value.
+    pub fn encode_delta(&self, delta: &WorldDelta) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&DELTA_VERSION.to_le_bytes());
+        self.entities.encode_delta(&delta.entities, &mut buf)?;
+
+        buf.extend_from_slice(&(delta.resources.len() as u32).to_le_bytes());
+        for (type_id, bytes) in &delta.resources {
+            let codec = self.resource_codecs.get(type_id).ok_or_else(
+                || Error::UnknownSnapshotComponent("delta holds a resource without a codec".to_string())
+            )?;
+            write_name(&mut buf, codec.name);
+            match bytes {
+                Some(bytes) => {
+                    buf.push(1);
+                    buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                    buf.extend_from_slice(bytes);
+                }
+                None => buf.push(0),
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Reads bytes from `encode_delta`, matching components and resources by codec name.
+    pub fn decode_delta(&self, bytes: &[u8]) -> Result<WorldDelta> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(Error::InvalidSnapshot("missing delta header".to_string()));
+        }
+        let version = reader.u16()?;
+        if version != DELTA_VERSION {
+            return Err(Error::InvalidSnapshot(format!("unsupported delta version {version}")));
+        }
+        let entities = self.entities.decode_delta(&mut reader)?;
+
+        let mut resources = vec![];
+        for _ in 0..reader.u32()? {
+            let name = read_name(&mut reader)?;
+            let type_id = self.resource_codecs
+                .iter()
+                .find(|(_, codec)| codec.name == name)
+                .map(|(type_id, _)| *type_id)
+                .ok_or_else(|| Error::UnknownSnapshotComponent(format!("{name} is not registered with a codec")))?;
+            let bytes = match reader.take(1)? {
+                [0] => None,
+                [1] => {
+                    let len = reader.u32()? as usize;
+                    Some(reader.take(len)?.to_vec())
+                }
+                _ => return Err(Error::InvalidSnapshot("invalid resource presence byte".to_string())),
+            };
+            resources.push((type_id, bytes));
+        }
+        if !reader.bytes.is_empty() {
+            return Err(Error::InvalidSnapshot("trailing bytes after delta".to_string()));
+        }
+        Ok(WorldDelta { entities, resources })
+    }
+}
+
+fn encode_resource_of(world: &World, type_id: &TypeId, codec: &ResourceCodec) -> Option<Vec<u8>> {
+    let data = world.resources.get_by_id(type_id)?;
+    let mut bytes = vec![];
+    (codec.encode)(data, &mut bytes);
+    Some(bytes)
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeSet;
+
+use super::Entites;
+
+/// All entities sharing the same component mask.
+#[derive(Default, Debug)]
+pub struct Archetype {
+    mask: u32,
+    entities: BTreeSet<usize>,
+}
+
+impl Archetype {
+    pub fn mask(&self) -> u32 {
+        self.mask
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = &usize> {
+        self.entities.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+}
+
+impl Entites {
+    /// Every write to the entity map goes through here so the archetypes stay in sync.
+    /// An entity left without components is dead, so its generation moves on.
+    pub(crate) fn set_mask(&mut self, index: usize, mask: u32) {
+        let old_mask = self.map[index];
+        if old_mask == mask {
+            return;
+        }
+        if let Some(archetype) = self.archetype_lookup.get(&old_mask) {
+            self.archetypes[*archetype].entities.remove(&index);
+        }
+        if mask != 0 {
+            let archetype = match self.archetype_lookup.get(&mask) {
+                Some(archetype) => *archetype,
+                None => {
+                    self.archetypes.push(Archetype { mask, entities: BTreeSet::new() });
+                    self.archetype_lookup.insert(mask, self.archetypes.len() - 1);
+                    self.archetypes.len() - 1
+                }
+            };
+            self.archetypes[archetype].entities.insert(index);
+        }
+        self.map[index] = mask;
+        self.index_removed(index, old_mask & !mask);
+        self.record_removed(index, old_mask & !mask);
+        if mask == 0 {
+            self.generations[index] = self.generations[index].wrapping_add(1);
+        }
+    }
+
+    pub(crate) fn add_to_mask(&mut self, index: usize, bits: u32) {
+        self.set_mask(index, self.map[index] | bits);
+    }
+
+    pub(crate) fn remove_from_mask(&mut self, index: usize, bits: u32) {
+        self.set_mask(index, self.map[index] & !bits);
+    }
+
+    /// Archetypes in the order they first appeared. New ones are only ever appended,
+    /// so a position in this list stays valid for the life of the world.
+    pub fn archetypes(&self) -> &[Archetype] {
+        &self.archetypes
+    }
+
+    pub(crate) fn clear_archetypes(&mut self) {
+        for archetype in &mut self.archetypes {
+            archetype.entities.clear();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn entities_move_between_archetypes() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?.with_component(2.0_f32)?;
+
+        let masks = entities.archetypes().iter().map(Archetype::mask).collect::<Vec<_>>();
+        assert_eq!(masks, vec![1, 3]);
+        assert_eq!(entities.archetypes()[0].len(), 1);
+
+        entities.delete_component_by_entity_id::<f32>(1)?;
+        assert_eq!(entities.archetypes()[0].entities().copied().collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(entities.archetypes()[1].len(), 0);
+
+        entities.delete_entity_by_id(0)?;
+        assert_eq!(entities.archetypes()[0].entities().copied().collect::<Vec<_>>(), vec![1]);
+        Ok(())
+    }
+}
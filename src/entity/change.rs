@@ -0,0 +1,104 @@
+use std::any::{Any, TypeId};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use super::Entites;
+
+/// When each component of each entity last changed. Ticks only ever grow and,
+/// being 64 bits wide, do not run out even at millions of changes per frame,
+/// so remembering `change_tick` and later asking `changed_since` finds
+/// everything that happened in between. Kept behind cells so code that only
+/// has `&Entites`, like systems writing through query results, can report changes.
+#[derive(Debug, Default)]
+pub(crate) struct ChangeTicks {
+    tick: Cell<u64>,
+    ticks: RefCell<HashMap<TypeId, Vec<u64>>>,
+}
+
+impl Entites {
+    pub fn change_tick(&self) -> u64 {
+        self.changes.tick.get()
+    }
+
+    /// Reports a change made through a component handed out by a query,
+    /// which `Entites` cannot see by itself.
+    pub fn mark_changed<T: Any>(&self, index: usize) {
+        self.record_change(&TypeId::of::<T>(), index);
+    }
+
+    /// Entities whose `T` was added, changed through `get_mut` or `mark_changed`,
+    /// or removed after `tick`. Removed ones no longer have `T`.
+    /// Checks the tick of every entity slot, so the cost grows with the
+    /// number of entities, not with the number of changes.
+    pub fn changed_since<T: Any>(&self, tick: u64) -> Vec<usize> {
+        self.changes.ticks
+            .borrow()
+            .get(&TypeId::of::<T>())
+            .map(|ticks| {
+                ticks.iter()
+                    .enumerate()
+                    .filter(|(_index, changed)| **changed > tick)
+                    .map(|(index, _changed)| index)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn record_change(&self, type_id: &TypeId, index: usize) {
+        let tick = self.changes.tick.get() + 1;
+        self.changes.tick.set(tick);
+        let mut ticks = self.changes.ticks.borrow_mut();
+        let ticks = ticks.entry(*type_id).or_default();
+        if ticks.len() <= index {
+            ticks.resize(index + 1, 0);
+        }
+        ticks[index] = tick;
+    }
+
+    /// Records a change for every component in `removed`.
+    pub(crate) fn record_removed(&self, index: usize, removed: u32) {
+        if removed == 0 {
+            return;
+        }
+        for (type_id, mask) in &self.bit_masks {
+            if removed & mask != 0 {
+                self.record_change(type_id, index);
+            }
+        }
+    }
+
+    /// For mutable access that does not go through `component_mut`, like reflection.
+    pub(crate) fn component_mutated(&mut self, type_id: &TypeId, index: usize) {
+        self.index_mutated(type_id, index);
+        self.record_change(type_id, index);
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn changes_after_a_tick_are_reported() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        for value in 0..4_u32 {
+            entities.create_entity().with_component(value)?.with_component(1.0_f32)?;
+        }
+        assert_eq!(entities.changed_since::<u32>(0), vec![0, 1, 2, 3]);
+
+        let tick = entities.change_tick();
+        *entities.get_mut::<u32>(1).unwrap() += 1;
+        entities.mark_changed::<u32>(2);
+        entities.delete_entity_by_id(3)?;
+        *entities.get_mut::<f32>(0).unwrap() += 1.0;
+
+        assert_eq!(entities.changed_since::<u32>(tick), vec![1, 2, 3]);
+        assert_eq!(entities.changed_since::<f32>(tick), vec![0, 3]);
+        assert!(entities.changed_since::<u32>(entities.change_tick()).is_empty());
+        Ok(())
+    }
+}
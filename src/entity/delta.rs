@@ -0,0 +1,507 @@
+use std::any::{Any, TypeId};
+use std::collections::HashSet;
+
+use super::handle::Entity;
+use super::snapshot::Reader;
+use super::{Component, Entites};
+use crate::entity::error::{Error, Result};
+
+pub type EqFn = fn(&dyn Any, &dyn Any) -> bool;
+
+fn eq_component<T: Any + PartialEq>(data: &dyn Any, other: &dyn Any) -> bool {
+    data.downcast_ref::<T>() == other.downcast_ref::<T>()
+}
+
+/// Components with a codec travel as their encoded bytes, others as a copy.
+#[derive(Debug, Clone)]
+enum DeltaValue {
+    Encoded(Vec<u8>),
+    Value(Component),
+}
+
+#[derive(Debug, Clone)]
+struct ComponentDelta {
+    entity: Entity,
+    type_id: TypeId,
+    value: DeltaValue,
+}
+
+/// What changed between two `Entites`, from `Entites::diff`.
+/// Slots are matched by index and generation, so an entity whose slot was reused
+/// shows up as despawned and spawned again.
+#[derive(Debug, Clone, Default)]
+pub struct EntitesDelta {
+    spawned: Vec<Entity>,
+    despawned: Vec<Entity>,
+    added: Vec<ComponentDelta>,
+    changed: Vec<ComponentDelta>,
+    removed: Vec<(Entity, TypeId)>,
+}
+
+impl EntitesDelta {
+    /// Includes entities without any compared component, see `Entites::diff`.
+    pub fn spawned(&self) -> &[Entity] {
+        &self.spawned
+    }
+
+    pub fn despawned(&self) -> &[Entity] {
+        &self.despawned
+    }
+
+    /// Components added to entities, including every component of spawned ones.
+    pub fn added(&self) -> Vec<(Entity, TypeId)> {
+        self.added.iter().map(|component| (component.entity, component.type_id)).collect()
+    }
+
+    pub fn changed(&self) -> Vec<(Entity, TypeId)> {
+        self.changed.iter().map(|component| (component.entity, component.type_id)).collect()
+    }
+
+    pub fn removed(&self) -> &[(Entity, TypeId)] {
+        &self.removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spawned.is_empty()
+            && self.despawned.is_empty()
+            && self.added.is_empty()
+            && self.changed.is_empty()
+            && self.removed.is_empty()
+    }
+}
+
+impl Entites {
+    /// Lets `diff` compare `T` without a codec. Such values are copied into the delta
+    /// in memory only and cannot be turned into bytes.
+    pub fn register_diff<T: Any + Clone + PartialEq>(&mut self) {
+        self.register_clone::<T>();
+        self.comparisons.insert(TypeId::of::<T>(), eq_component::<T>);
+    }
+
+    /// Components with a codec, tags and components registered with `register_diff`.
+    fn is_diffable(&self, type_id: &TypeId) -> bool {
+        self.codecs.contains_key(type_id) || self.is_tag(type_id) || self.comparisons.contains_key(type_id)
+    }
+
+    /// Everything `apply` needs to turn `self` into `other`. Components are taken
+    /// from the registrations of `self`; other components and dynamic components are left out.
+    /// An entity whose components are all left out is still listed as spawned, so its
+    /// slot and generation carry over, but it is not alive after `apply` since it has
+    /// no components there. It comes to life once a later delta adds one.
+    pub fn diff(&self, other: &Entites) -> Result<EntitesDelta> {
+        let mut tracked = self.bit_masks.keys().filter(|type_id| self.is_diffable(type_id)).copied().collect::<Vec<_>>();
+        tracked.sort_by_key(|type_id| self.bit_masks[type_id]);
+
+        let mut delta = EntitesDelta::default();
+        for index in 0..self.map.len().max(other.map.len()) {
+            let before = self.entity_handle(index);
+            let after = other.entity_handle(index);
+            if before != after {
+                delta.despawned.extend(before);
+                delta.spawned.extend(after);
+            }
+            let Some(entity) = after else {
+                continue;
+            };
+            for type_id in &tracked {
+                let old = if before == after { self.diffed_component(type_id, index) } else { None };
+                match (old, other.diffed_component(type_id, index)) {
+                    (None, Some(new)) => {
+                        let value = self.delta_value(type_id, new)?;
+                        delta.added.push(ComponentDelta { entity, type_id: *type_id, value });
+                    }
+                    (Some(_old), None) => delta.removed.push((entity, *type_id)),
+                    (Some(old), Some(new)) if !self.values_equal(type_id, old, new) => {
+                        let value = self.delta_value(type_id, new)?;
+                        delta.changed.push(ComponentDelta { entity, type_id: *type_id, value });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(delta)
+    }
+
+    fn diffed_component(&self, type_id: &TypeId, index: usize) -> Option<&Component> {
+        let mask = self.get_bitmask(type_id)?;
+        if !self.contains_component(index, mask) {
+            return None;
+        }
+        self.component_at(type_id, index)
+    }
+
+    fn values_equal(&self, type_id: &TypeId, old: &Component, new: &Component) -> bool {
+        if let Some(codec) = self.codecs.get(type_id) {
+            let (mut old_bytes, mut new_bytes) = (vec![], vec![]);
+            (codec.encode)(&*old.borrow(), &mut old_bytes);
+            (codec.encode)(&*new.borrow(), &mut new_bytes);
+            old_bytes == new_bytes
+        } else if let Some(eq) = self.comparisons.get(type_id) {
+            eq(&*old.borrow(), &*new.borrow())
+        } else {
+            self.is_tag(type_id)
+        }
+    }
+
+    fn delta_value(&self, type_id: &TypeId, component: &Component) -> Result<DeltaValue> {
+        match self.codecs.get(type_id) {
+            Some(codec) => {
+                let mut bytes = vec![];
+                (codec.encode)(&*component.borrow(), &mut bytes);
+                Ok(DeltaValue::Encoded(bytes))
+            }
+            None => Ok(DeltaValue::Value(self.clone_value(type_id, component)?)),
+        }
+    }
+
+    fn component_from_delta(&self, component: &ComponentDelta) -> Result<Component> {
+        match &component.value {
+            DeltaValue::Encoded(bytes) => {
+                let codec = self.codecs.get(&component.type_id).ok_or_else(
+                    || Error::UnknownSnapshotComponent("delta holds a component without a codec".to_string())
+                )?;
+                (codec.decode)(bytes).ok_or_else(|| Error::InvalidSnapshot(format!("could not decode {}", codec.name)))
+            }
+            DeltaValue::Value(value) => self.clone_value(&component.type_id, value),
+        }
+    }
+
+    /// Applies a delta made by `diff`, keeping indexes and generations as they were in
+    /// the other `Entites`. Nothing changes when the delta does not fit, e.g. when an
+    /// entity it despawns is not alive or a unique component would end up twice.
+    /// Removals happen before insertions, so unique components can move between entities.
+    pub fn apply(&mut self, delta: &EntitesDelta) -> Result<()> {
+        for entity in &delta.despawned {
+            if !self.is_alive(*entity) {
+                return Err(Error::EntityDoesNotExist(format!("entity {} is not alive", entity.index())));
+            }
+        }
+        let freed = delta.despawned.iter().map(|entity| entity.index()).collect::<HashSet<_>>();
+        let slot_limit = self.map.len() + delta.spawned.len();
+        for entity in &delta.spawned {
+            if entity.index() >= slot_limit {
+                return Err(Error::InvalidSnapshot(format!("delta spawns entity {} past the end of the world", entity.index())));
+            }
+            let taken = self.map.get(entity.index()).is_some_and(|mask| *mask != 0);
+            if taken && !freed.contains(&entity.index()) {
+                return Err(Error::DuplicateEntity(format!("entity {} is already alive", entity.index())));
+            }
+        }
+        let exists = |entity: &Entity| {
+            delta.spawned.contains(entity) || (self.is_alive(*entity) && !delta.despawned.contains(entity))
+        };
+        let mut inserted = vec![];
+        for component in delta.added.iter().chain(&delta.changed) {
+            if !exists(&component.entity) {
+                return Err(Error::EntityDoesNotExist(format!("entity {} is not alive", component.entity.index())));
+            }
+            inserted.push((component.entity.index(), component.type_id, self.component_from_delta(component)?));
+        }
+        for (entity, type_id) in &delta.removed {
+            if !exists(entity) {
+                return Err(Error::EntityDoesNotExist(format!("entity {} is not alive", entity.index())));
+            }
+            if self.get_bitmask(type_id).is_none() {
+                return Err(Error::ComponetNotRegister("delta removes a component that wasn't registerd".to_string()));
+            }
+        }
+        self.check_unique_after(delta)?;
+
+        for entity in &delta.despawned {
+            self.despawn_alone(entity, &freed)?;
+        }
+        for entity in &delta.spawned {
+            while self.map.len() <= entity.index() {
+                self.push_slot();
+            }
+            self.generations[entity.index()] = entity.generation();
+        }
+        for (entity, type_id) in &delta.removed {
+            self.delete_component(*type_id, entity.index())?;
+        }
+        for (index, type_id, component) in inserted {
+            self.insert_component(type_id, index, component)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the entity like `delete_entity_by_id`, but children the delta keeps
+    /// are detached first instead of being deleted along.
+    fn despawn_alone(&mut self, entity: &Entity, despawned: &HashSet<usize>) -> Result<()> {
+        if !self.is_alive(*entity) {
+            return Ok(());
+        }
+        for child in self.children(entity.index()) {
+            if !despawned.contains(&child) {
+                self.remove_child(entity.index(), child)?;
+            }
+        }
+        self.delete_entity_by_id(entity.index())
+    }
+
+    /// Fails when a unique component would be held by more than one entity once `delta` is applied.
+    fn check_unique_after(&self, delta: &EntitesDelta) -> Result<()> {
+        for component in delta.added.iter().chain(&delta.changed) {
+            let type_id = component.type_id;
+            let Some(mask) = self.get_bitmask(&type_id).filter(|_mask| self.is_unique(&type_id)) else {
+                continue;
+            };
+            let mut holders = self.holders_of(mask)
+                .into_iter()
+                .filter(|index| {
+                    !delta.despawned.iter().any(|entity| entity.index() == *index)
+                        && !delta.removed.iter().any(|(entity, removed)| entity.index() == *index && *removed == type_id)
+                })
+                .collect::<HashSet<_>>();
+            holders.extend(
+                delta.added.iter().chain(&delta.changed)
+                    .filter(|other| other.type_id == type_id)
+                    .map(|other| other.entity.index())
+            );
+            if holders.len() > 1 {
+                return Err(Error::UniqueComponentTaken(
+                    format!("applying the delta would give this unique component to {} entities", holders.len())
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Appends the delta to `buf`. Every component in it needs a codec.
+    /// Components are written by codec name, entities as index and generation.
+    pub fn encode_delta(&self, delta: &EntitesDelta, buf: &mut Vec<u8>) -> Result<()> {
+        for entities in [&delta.despawned, &delta.spawned] {
+            buf.extend_from_slice(&(entities.len() as u32).to_le_bytes());
+            for entity in entities {
+                write_entity(buf, entity);
+            }
+        }
+        for components in [&delta.added, &delta.changed] {
+            buf.extend_from_slice(&(components.len() as u32).to_le_bytes());
+            for component in components {
+                let DeltaValue::Encoded(bytes) = &component.value else {
+                    return Err(Error::UnknownSnapshotComponent("delta holds a component without a codec".to_string()));
+                };
+                self.write_component_name(buf, &component.type_id)?;
+                write_entity(buf, &component.entity);
+                buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+                buf.extend_from_slice(bytes);
+            }
+        }
+        buf.extend_from_slice(&(delta.removed.len() as u32).to_le_bytes());
+        for (entity, type_id) in &delta.removed {
+            self.write_component_name(buf, type_id)?;
+            write_entity(buf, entity);
+        }
+        Ok(())
+    }
+
+    fn write_component_name(&self, buf: &mut Vec<u8>, type_id: &TypeId) -> Result<()> {
+        let codec = self.codecs.get(type_id).ok_or_else(
+            || Error::UnknownSnapshotComponent("delta holds a component without a codec".to_string())
+        )?;
+        write_name(buf, codec.name);
+        Ok(())
+    }
+
+    /// Reads what `encode_delta` wrote, matching components by codec name.
+    pub(crate) fn decode_delta(&self, reader: &mut Reader) -> Result<EntitesDelta> {
+        let mut delta = EntitesDelta::default();
+        for entities in [&mut delta.despawned, &mut delta.spawned] {
+            for _ in 0..reader.u32()? {
+                entities.push(read_entity(reader)?);
+            }
+        }
+        for components in [&mut delta.added, &mut delta.changed] {
+            for _ in 0..reader.u32()? {
+                let type_id = self.read_component_name(reader)?;
+                let entity = read_entity(reader)?;
+                let len = reader.u32()? as usize;
+                let value = DeltaValue::Encoded(reader.take(len)?.to_vec());
+                components.push(ComponentDelta { entity, type_id, value });
+            }
+        }
+        for _ in 0..reader.u32()? {
+            let type_id = self.read_component_name(reader)?;
+            delta.removed.push((read_entity(reader)?, type_id));
+        }
+        Ok(delta)
+    }
+
+    fn read_component_name(&self, reader: &mut Reader) -> Result<TypeId> {
+        let name = read_name(reader)?;
+        self.codecs
+            .iter()
+            .find(|(_, codec)| codec.name == name)
+            .map(|(type_id, _)| *type_id)
+            .ok_or_else(|| Error::UnknownSnapshotComponent(format!("{name} is not registered with a codec")))
+    }
+}
+
+pub(crate) fn write_name(buf: &mut Vec<u8>, name: &str) {
+    buf.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    buf.extend_from_slice(name.as_bytes());
+}
+
+pub(crate) fn read_name<'a>(reader: &mut Reader<'a>) -> Result<&'a str> {
+    let len = reader.u16()? as usize;
+    std::str::from_utf8(reader.take(len)?)
+        .map_err(|_| Error::InvalidSnapshot("component name is not utf8".to_string()))
+}
+
+fn write_entity(buf: &mut Vec<u8>, entity: &Entity) {
+    buf.extend_from_slice(&(entity.index() as u32).to_le_bytes());
+    buf.extend_from_slice(&entity.generation().to_le_bytes());
+}
+
+fn read_entity(reader: &mut Reader) -> Result<Entity> {
+    let index = reader.u32()? as usize;
+    Ok(Entity::new(index, reader.u32()?))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Label(&'static str);
+
+    fn initialize_entities() -> Result<Entites> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_codec::<u32>();
+        entities.register_diff::<Label>();
+        entities.create_entity().with_component(1_u32)?.with_component(Label("a"))?;
+        entities.create_entity().with_component(2_u32)?;
+        entities.create_entity().with_component(3_u32)?;
+        Ok(entities)
+    }
+
+    #[test]
+    fn diff_finds_every_kind_of_change() -> Result<()> {
+        let before = initialize_entities()?;
+        let mut after = initialize_entities()?;
+        after.delete_entity_by_id(1)?;
+        after.delete_component_by_entity_id::<Label>(0)?;
+        *after.get_mut::<u32>(2).unwrap() = 30;
+        after.add_component_to_entity_by_id(Label("c"), 2)?;
+        after.create_entity().with_component(4_u32)?;
+
+        let delta = before.diff(&after)?;
+
+        let respawned = after.entity_handle(1).unwrap();
+        let third = after.entity_handle(2).unwrap();
+        assert_eq!(delta.despawned(), &[before.entity_handle(1).unwrap()]);
+        assert_eq!(delta.spawned(), &[respawned]);
+        assert_eq!(delta.added(), vec![(respawned, TypeId::of::<u32>()), (third, TypeId::of::<Label>())]);
+        assert_eq!(delta.changed(), vec![(third, TypeId::of::<u32>())]);
+        assert_eq!(delta.removed(), &[(after.entity_handle(0).unwrap(), TypeId::of::<Label>())]);
+        assert!(after.diff(&after)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn apply_reproduces_the_other_entities() -> Result<()> {
+        let mut before = initialize_entities()?;
+        let mut after = initialize_entities()?;
+        after.delete_entity_by_id(0)?;
+        after.create_entity().with_component(5_u32)?.with_component(Label("b"))?;
+        after.create_entity().with_component(6_u32)?;
+
+        before.apply(&before.diff(&after)?)?;
+
+        assert_eq!(before.map, after.map);
+        assert_eq!(before.generations, after.generations);
+        assert_eq!(*before.get::<u32>(3).unwrap(), 6);
+        assert!(before.diff(&after)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn unique_components_can_move_between_entities() -> Result<()> {
+        let with_unique_on = |index: usize| -> Result<Entites> {
+            let mut entities = initialize_entities()?;
+            entities.register_unique_component::<u8>();
+            entities.register_codec::<u8>();
+            entities.add_component_to_entity_by_id(1_u8, index)?;
+            Ok(entities)
+        };
+        let mut before = with_unique_on(0)?;
+        let delta = before.diff(&with_unique_on(1)?)?;
+
+        let mut taken = with_unique_on(2)?;
+        assert!(matches!(taken.apply(&delta), Err(Error::UniqueComponentTaken(_))));
+        assert!(taken.has::<u8>(2) && !taken.has::<u8>(1));
+
+        before.apply(&delta)?;
+        assert!(!before.has::<u8>(0));
+        assert!(before.has::<u8>(1));
+        Ok(())
+    }
+
+    #[test]
+    fn despawns_clean_up_hierarchy_and_relations() -> Result<()> {
+        struct Likes;
+        let with_links = || -> Result<Entites> {
+            let mut entities = initialize_entities()?;
+            entities.set_parent(1, 0)?;
+            entities.add_relation::<Likes>(2, 0)?;
+            Ok(entities)
+        };
+        let mut before = with_links()?;
+        let mut after = with_links()?;
+        after.remove_child(0, 1)?;
+        after.delete_entity_by_id(0)?;
+
+        before.apply(&before.diff(&after)?)?;
+
+        assert!(before.entity_handle(0).is_none());
+        assert!(before.entity_handle(1).is_some());
+        assert_eq!(before.parent(1), None);
+        assert!(before.relation_targets::<Likes>(2).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn spawns_without_diffable_components_stay_dead() -> Result<()> {
+        let mut before = initialize_entities()?;
+        before.register_component::<f32>();
+        let mut after = initialize_entities()?;
+        after.register_component::<f32>();
+        after.create_entity().with_component(1.5_f32)?;
+        let spawned = after.entity_handle(3).unwrap();
+
+        let delta = before.diff(&after)?;
+        assert_eq!(delta.spawned(), &[spawned]);
+        assert!(delta.added().is_empty());
+        before.apply(&delta)?;
+        assert!(!before.is_alive(spawned));
+
+        after.add_component_to_entity_by_id(4_u32, 3)?;
+        before.apply(&before.diff(&after)?)?;
+        assert!(before.is_alive(spawned));
+        assert_eq!(*before.get::<u32>(3).unwrap(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn apply_rejects_deltas_that_do_not_fit() -> Result<()> {
+        let before = initialize_entities()?;
+        let mut after = initialize_entities()?;
+        after.delete_entity_by_id(2)?;
+        let delta = before.diff(&after)?;
+
+        let mut other = initialize_entities()?;
+        other.delete_entity_by_id(2)?;
+        assert!(matches!(other.apply(&delta), Err(Error::EntityDoesNotExist(_))));
+        assert_eq!(other.map, vec![3, 1, 0]);
+
+        let mut far = EntitesDelta::default();
+        far.spawned.push(Entity::new(u32::MAX as usize, 0));
+        assert!(matches!(other.apply(&far), Err(Error::InvalidSnapshot(_))));
+        assert_eq!(other.map.len(), 3);
+        Ok(())
+    }
+}
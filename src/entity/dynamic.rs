@@ -0,0 +1,169 @@
+use std::cell::{Ref, RefCell, RefMut};
+use std::rc::Rc;
+
+use super::Entites;
+use crate::entity::error::{Error, Result};
+
+/// Identifies a component type that was defined at runtime.
+/// The data of a dynamic component is stored as a byte blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ComponentId(u32);
+
+impl Entites {
+    /// Registering the same name twice returns the id of the first registration.
+    /// Fails with `TooManyComponents` once all bits of the entity map are taken.
+    pub fn register_dynamic_component(&mut self, name: &str) -> Result<ComponentId> {
+        if let Some(id) = self.dynamic_component_id(name) {
+            return Ok(id);
+        }
+        let id = ComponentId(self.dynamic_names.len() as u32);
+        let bit_mask = self.next_bit_mask()?;
+        self.dynamic_components.insert(id, vec![None; self.map.len()]);
+        self.dynamic_bit_masks.insert(id, bit_mask);
+        self.dynamic_names.push(name.to_string());
+        Ok(id)
+    }
+
+    pub fn dynamic_component_id(&self, name: &str) -> Option<ComponentId> {
+        self.dynamic_names
+            .iter()
+            .position(|registered| registered == name)
+            .map(|index| ComponentId(index as u32))
+    }
+
+    pub fn dynamic_component_name(&self, id: ComponentId) -> Option<&str> {
+        self.dynamic_names.get(id.0 as usize).map(String::as_str)
+    }
+
+    pub fn dynamic_components_of(&self, index: usize) -> Vec<ComponentId> {
+        let mut ids = self.dynamic_bit_masks
+            .iter()
+            .filter(|(_id, mask)| self.contains_component(index, **mask))
+            .map(|(id, _mask)| *id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    pub fn get_dynamic_bitmask(&self, id: &ComponentId) -> Option<u32> {
+        self.dynamic_bit_masks.get(id).copied()
+    }
+
+    pub fn with_dynamic_component(&mut self, id: ComponentId, data: Vec<u8>) -> Result<&mut Self> {
+        let index = self.inserting_into_index;
+        self.add_dynamic_component_to_entity_by_id(id, data, index)?;
+        Ok(self)
+    }
+
+    pub fn add_dynamic_component_to_entity_by_id(&mut self, id: ComponentId, data: Vec<u8>, index: usize) -> Result<()> {
+        let mask = self.get_dynamic_bitmask(&id).ok_or_else(
+            || Error::ComponetNotRegister("attempting use dynamic component that wasn't registerd".to_string())
+        )?;
+        let component = self.dynamic_components
+            .get_mut(&id)
+            .and_then(|components| components.get_mut(index))
+            .ok_or_else(
+                || Error::EntityDoesNotExist("attempting to add a component to an entity that does not exist".to_string())
+            )?;
+        *component = Some(Rc::new(RefCell::new(data)));
+        self.add_to_mask(index, mask);
+
+        Ok(())
+    }
+
+    pub fn delete_dynamic_component_by_entity_id(&mut self, id: ComponentId, index: usize) -> Result<()> {
+        let mask = self.get_dynamic_bitmask(&id).ok_or_else(
+            || Error::ComponetNotRegister("attempting use dynamic component that wasn't registerd".to_string())
+        )?;
+        if index >= self.map.len() {
+            return Err(Error::EntityDoesNotExist(format!("entity {index} does not exist")));
+        }
+        if self.has_component(index, mask) {
+            self.remove_from_mask(index, mask);
+        }
+        if let Some(slot) = self.dynamic_components.get_mut(&id).and_then(|components| components.get_mut(index)) {
+            *slot = None;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_dynamic_component(&self, id: ComponentId, index: usize) -> Option<Ref<'_, Vec<u8>>> {
+        let mask = self.get_dynamic_bitmask(&id)?;
+        if !self.contains_component(index, mask) {
+            return None;
+        }
+        let component = self.dynamic_components.get(&id)?.get(index)?.as_ref()?;
+        Ref::filter_map(component.borrow(), |data| data.downcast_ref::<Vec<u8>>()).ok()
+    }
+
+    pub fn get_dynamic_component_mut(&mut self, id: ComponentId, index: usize) -> Option<RefMut<'_, Vec<u8>>> {
+        let mask = self.get_dynamic_bitmask(&id)?;
+        if !self.contains_component(index, mask) {
+            return None;
+        }
+        let component = self.dynamic_components.get(&id)?.get(index)?.as_ref()?;
+        RefMut::filter_map(component.borrow_mut(), |data| data.downcast_mut::<Vec<u8>>()).ok()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn dynamic_components_share_bits_with_static_ones() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        let armor = entities.register_dynamic_component("armor")?;
+        entities.register_component::<f32>();
+
+        assert_eq!(entities.get_dynamic_bitmask(&armor), Some(2));
+        assert_eq!(entities.get_bitmask(&std::any::TypeId::of::<f32>()), Some(4));
+        assert_eq!(entities.register_dynamic_component("armor")?, armor);
+        assert_eq!(entities.dynamic_component_name(armor), Some("armor"));
+        Ok(())
+    }
+
+    #[test]
+    fn registering_past_the_last_bit_fails() -> Result<()> {
+        let mut entities = Entites::default();
+        for bit in 0..u32::BITS {
+            entities.register_dynamic_component(&bit.to_string())?;
+        }
+        assert_eq!(entities.get_dynamic_bitmask(&ComponentId(31)), Some(1 << 31));
+        assert!(matches!(entities.register_dynamic_component("one more"), Err(Error::TooManyComponents(_))));
+        assert!(matches!(
+            entities.try_register_component_with_storage::<u32>(crate::StorageType::Table),
+            Err(Error::TooManyComponents(_))
+        ));
+        assert!(entities.get_bitmask(&std::any::TypeId::of::<u32>()).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn insert_and_read_dynamic_component() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        let armor = entities.register_dynamic_component("armor")?;
+        entities.create_entity()
+            .with_component(10_u32)?
+            .with_dynamic_component(armor, vec![1, 2, 3])?;
+        entities.create_entity().with_component(20_u32)?;
+
+        assert_eq!(entities.map, vec![3, 1]);
+        assert_eq!(*entities.get_dynamic_component(armor, 0).unwrap(), vec![1, 2, 3]);
+        assert!(entities.get_dynamic_component(armor, 1).is_none());
+
+        entities.get_dynamic_component_mut(armor, 0).unwrap().push(4);
+        assert_eq!(*entities.get_dynamic_component(armor, 0).unwrap(), vec![1, 2, 3, 4]);
+
+        entities.delete_dynamic_component_by_entity_id(armor, 0)?;
+        assert_eq!(entities.map[0], 1);
+        assert!(entities.get_dynamic_component(armor, 0).is_none());
+        assert!(matches!(entities.delete_dynamic_component_by_entity_id(armor, 9), Err(Error::EntityDoesNotExist(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefMut};
+
+use super::dynamic::ComponentId;
+use super::{Component, Entites};
+use crate::entity::error::{Error, Result};
+
+/// Read only view of one entity and the components it has.
+#[derive(Debug)]
+pub struct EntityRef<'a> {
+    entities: &'a Entites,
+    index: usize,
+}
+
+/// Mutable view of one entity that can also add and remove components.
+#[derive(Debug)]
+pub struct EntityMut<'a> {
+    entities: &'a mut Entites,
+    index: usize,
+}
+
+impl<'a> EntityRef<'a> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn get<T: Any>(&self) -> Option<Ref<'a, T>> {
+        self.entities.get::<T>(self.index)
+    }
+
+    pub fn contains<T: Any>(&self) -> bool {
+        self.entities.has::<T>(self.index)
+    }
+
+    pub fn components(&self) -> Vec<(TypeId, Component)> {
+        self.entities.components_of(self.index).unwrap_or_default()
+    }
+
+    pub fn type_ids(&self) -> Vec<TypeId> {
+        self.components().into_iter().map(|(type_id, _component)| type_id).collect()
+    }
+
+    pub fn dynamic_component_ids(&self) -> Vec<ComponentId> {
+        self.entities.dynamic_components_of(self.index)
+    }
+}
+
+impl EntityMut<'_> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn get<T: Any>(&self) -> Option<Ref<'_, T>> {
+        self.entities.get::<T>(self.index)
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> Option<RefMut<'_, T>> {
+        self.entities.get_mut::<T>(self.index)
+    }
+
+    pub fn contains<T: Any>(&self) -> bool {
+        self.entities.has::<T>(self.index)
+    }
+
+    pub fn insert(&mut self, data: impl Any) -> Result<&mut Self> {
+        self.entities.add_component_to_entity_by_id(data, self.index)?;
+        Ok(self)
+    }
+
+    pub fn remove<T: Any>(&mut self) -> Result<&mut Self> {
+        self.entities.delete_component_by_entity_id::<T>(self.index)?;
+        Ok(self)
+    }
+
+    pub fn as_ref(&self) -> EntityRef<'_> {
+        EntityRef { entities: self.entities, index: self.index }
+    }
+}
+
+impl Entites {
+    pub fn get<T: Any>(&self, index: usize) -> Option<Ref<'_, T>> {
+        self.component_ref::<T>(index)
+    }
+
+    /// `None` when the entity lacks `T` or `T` is a tag.
+    pub fn get_mut<T: Any>(&mut self, index: usize) -> Option<RefMut<'_, T>> {
+        self.index_mutated(&TypeId::of::<T>(), index);
+        self.component_mut::<T>(index)
+    }
+
+    pub fn has<T: Any>(&self, index: usize) -> bool {
+        self.get_bitmask(&TypeId::of::<T>())
+            .is_some_and(|mask| self.contains_component(index, mask))
+    }
+
+    /// Mutable access to `T` on several entities at once.
+    /// Fails when an index is repeated, an entity lacks the component, `T` is a tag
+    /// or one of the values is already borrowed.
+    pub fn get_many_mut<T: Any>(&mut self, indexes: &[usize]) -> Result<Vec<RefMut<'_, T>>> {
+        self.ensure_not_tag::<T>()?;
+        for (position, index) in indexes.iter().enumerate() {
+            if indexes[..position].contains(index) {
+                return Err(Error::DuplicateEntity(format!("entity {index} was requested more than once")));
+            }
+        }
+        for index in indexes {
+            self.index_mutated(&TypeId::of::<T>(), *index);
+        }
+        indexes
+            .iter()
+            .map(|index| {
+                if !self.has::<T>(*index) {
+                    return Err(Error::ComponentNotFound(format!("entity {index} has no {}", std::any::type_name::<T>())));
+                }
+                let component = self.component_at(&TypeId::of::<T>(), *index).unwrap();
+                let borrowed = component.try_borrow_mut().map_err(
+                    |_| Error::ComponentAlreadyBorrowed(format!("{} of entity {index} is already borrowed", std::any::type_name::<T>()))
+                )?;
+                self.record_change(&TypeId::of::<T>(), *index);
+                Ok(RefMut::map(borrowed, |data| data.downcast_mut::<T>().unwrap()))
+            })
+            .collect()
+    }
+
+    pub fn entity(&self, index: usize) -> Result<EntityRef<'_>> {
+        self.ensure_exists(index)?;
+        Ok(EntityRef { entities: self, index })
+    }
+
+    pub fn entity_mut(&mut self, index: usize) -> Result<EntityMut<'_>> {
+        self.ensure_exists(index)?;
+        Ok(EntityMut { entities: self, index })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn get_and_get_mut() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        entities.create_entity().with_component(10_u32)?;
+
+        *entities.get_mut::<u32>(0).unwrap() += 1;
+
+        assert_eq!(*entities.get::<u32>(0).unwrap(), 11);
+        assert!(entities.has::<u32>(0));
+        assert!(!entities.has::<f32>(0));
+        assert!(entities.get::<f32>(0).is_none());
+        assert!(entities.get::<u32>(1).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn get_many_mut_rejects_duplicates() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?;
+
+        {
+            let mut values = entities.get_many_mut::<u32>(&[1, 0])?;
+            let (first, second) = values.split_at_mut(1);
+            std::mem::swap(&mut *first[0], &mut *second[0]);
+        }
+        assert_eq!(*entities.get::<u32>(0).unwrap(), 2);
+
+        assert!(matches!(entities.get_many_mut::<u32>(&[0, 0]), Err(Error::DuplicateEntity(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn get_many_mut_reports_held_borrows() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?;
+        let held = entities.components_of(1)?.remove(0).1;
+        let _borrowed = held.borrow();
+
+        assert!(matches!(entities.get_many_mut::<u32>(&[0, 1]), Err(Error::ComponentAlreadyBorrowed(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn tags_are_never_borrowed_mutably() -> Result<()> {
+        struct Frozen;
+        let mut entities = Entites::default();
+        entities.register_component::<Frozen>();
+        entities.create_entity().with_component(Frozen)?;
+        entities.create_entity().with_component(Frozen)?;
+
+        assert!(entities.get_mut::<Frozen>(0).is_none());
+        assert!(matches!(entities.get_many_mut::<Frozen>(&[0, 1]), Err(Error::TagNotMutable(_))));
+        assert!(entities.get::<Frozen>(0).is_some() && entities.get::<Frozen>(1).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn entity_mut_inserts_and_removes() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        entities.create_entity().with_component(1_u32)?;
+
+        let mut entity = entities.entity_mut(0)?;
+        entity.insert(2.5_f32)?.remove::<u32>()?;
+        assert_eq!(entity.as_ref().type_ids(), vec![TypeId::of::<f32>()]);
+
+        assert_eq!(entities.map[0], 2);
+        assert!(entities.entity(1).is_err());
+        Ok(())
+    }
+}
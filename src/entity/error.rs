@@ -7,7 +7,22 @@ pub type Result<T>  = core::result::Result<T, Error>;
 pub enum Error {
     ComponentNotFound(String),
     ComponetNotRegister(String),	
-	EntityDoesNotExist(String)
+	EntityDoesNotExist(String),
+	InvalidSnapshot(String),
+	UnknownSnapshotComponent(String),
+	MismatchedSnapshotComponent(String),
+	ComponentNotReflectable(String),
+	InvalidHierarchy(String),
+	DuplicateEntity(String),
+	NotExactlyOneMatch(String),
+	ComponentAlreadyBorrowed(String),
+	MissingIndex(String),
+	UniqueComponentTaken(String),
+	ComponentNotCloneable(String),
+	InvalidScene(String),
+	TagNotMutable(String),
+	InvalidCellSize(String),
+	TooManyComponents(String),
 }
 
 
@@ -0,0 +1,80 @@
+use super::Entites;
+
+/// A handle to an entity that stays valid only as long as the entity does.
+/// Once the entity is deleted its slot can be reused, but the handle keeps
+/// the old generation and will no longer be considered alive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: usize,
+    generation: u32,
+}
+
+impl Entity {
+    pub(crate) fn new(index: usize, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl Entites {
+    /// Handle for the entity currently stored at `index`, if there is one.
+    pub fn entity_handle(&self, index: usize) -> Option<Entity> {
+        if self.map.get(index).copied().unwrap_or(0) == 0 {
+            return None;
+        }
+        Some(Entity { index, generation: self.generations[index] })
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entity_handle(entity.index) == Some(entity)
+    }
+
+    /// Handles of every entity that currently has components, by index.
+    pub fn iter_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        (0..self.map.len()).filter_map(|index| self.entity_handle(index))
+    }
+
+    /// Index `with_component` adds to, even before the entity has any component.
+    pub(crate) fn current_index(&self) -> usize {
+        self.inserting_into_index
+    }
+
+    /// Handle for the entity `with_component` is currently adding to.
+    pub fn current_entity(&self) -> Option<Entity> {
+        self.entity_handle(self.inserting_into_index)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn reused_slots_get_a_new_generation() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+        let first = entities.current_entity().unwrap();
+
+        entities.delete_entity_by_id(0)?;
+        assert!(!entities.is_alive(first));
+        assert!(entities.entity_handle(0).is_none());
+
+        entities.create_entity().with_component(2_u32)?;
+        let second = entities.current_entity().unwrap();
+        assert_eq!(second.index(), first.index());
+        assert_eq!(second.generation(), first.generation() + 1);
+        assert!(entities.is_alive(second));
+        assert!(!entities.is_alive(first));
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::VecDeque;
+
+use super::transfer::{EntityMap, MapEntities};
+use super::Entites;
+use crate::entity::error::{Error, Result};
+
+/// Points at the parent entity. Kept in sync with `Children` by the hierarchy methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub(crate) usize);
+
+impl Parent {
+    pub fn get(&self) -> usize {
+        self.0
+    }
+}
+
+/// Lists the child entities in the order they were added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(pub(crate) Vec<usize>);
+
+impl Children {
+    pub fn as_slice(&self) -> &[usize] {
+        &self.0
+    }
+}
+
+/// A parent that was not transferred along is dropped.
+impl MapEntities for Parent {
+    fn map_entities(&mut self, map: &EntityMap) -> bool {
+        match map.get(self.0) {
+            Some(parent) => {
+                self.0 = parent;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl MapEntities for Children {
+    fn map_entities(&mut self, map: &EntityMap) -> bool {
+        self.0.retain(|child| map.get(*child).is_some());
+        for child in &mut self.0 {
+            *child = map.get(*child).unwrap();
+        }
+        !self.0.is_empty()
+    }
+}
+
+impl Entites {
+    fn register_hierarchy(&mut self) {
+        self.register_clone::<Parent>();
+        self.register_clone::<Children>();
+        self.register_map_entities::<Parent>();
+        self.register_map_entities::<Children>();
+    }
+
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        self.component_ref::<Parent>(index).map(|parent| parent.0)
+    }
+
+    pub fn children(&self, index: usize) -> Vec<usize> {
+        self.component_ref::<Children>(index)
+            .map(|children| children.0.clone())
+            .unwrap_or_default()
+    }
+
+    /// Moves `child` under `parent`, detaching it from its previous parent.
+    pub fn set_parent(&mut self, child: usize, parent: usize) -> Result<()> {
+        self.ensure_exists(child)?;
+        self.ensure_exists(parent)?;
+        if child == parent || self.ancestors(parent).any(|ancestor| ancestor == child) {
+            return Err(Error::InvalidHierarchy(
+                format!("making {parent} the parent of {child} would create a cycle")
+            ));
+        }
+        self.register_hierarchy();
+
+        if let Some(old_parent) = self.parent(child) {
+            self.remove_child(old_parent, child)?;
+        }
+        self.add_component_to_entity_by_id(Parent(parent), child)?;
+        let added = self.component_mut::<Children>(parent)
+            .map(|mut children| children.0.push(child))
+            .is_some();
+        if !added {
+            self.add_component_to_entity_by_id(Children(vec![child]), parent)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn add_child(&mut self, parent: usize, child: usize) -> Result<()> {
+        self.set_parent(child, parent)
+    }
+
+    pub fn remove_child(&mut self, parent: usize, child: usize) -> Result<()> {
+        if self.parent(child) != Some(parent) {
+            return Err(Error::InvalidHierarchy(format!("{child} is not a child of {parent}")));
+        }
+        self.delete_component_by_entity_id::<Parent>(child)?;
+
+        let no_children_left = self.component_mut::<Children>(parent)
+            .map(|mut children| {
+                children.0.retain(|index| *index != child);
+                children.0.is_empty()
+            })
+            .unwrap_or(false);
+        if no_children_left {
+            self.delete_component_by_entity_id::<Children>(parent)?;
+        }
+
+        Ok(())
+    }
+
+    /// Parent, grandparent and so on up to the root.
+    pub fn ancestors(&self, index: usize) -> Ancestors<'_> {
+        Ancestors { entities: self, current: index }
+    }
+
+    /// Every entity below `index`, breadth first, not including `index` itself.
+    pub fn descendants(&self, index: usize) -> Descendants<'_> {
+        Descendants { entities: self, queue: self.children(index).into() }
+    }
+
+    /// `index` followed by its subtree in depth first pre-order.
+    pub fn depth_first(&self, index: usize) -> DepthFirst<'_> {
+        DepthFirst { entities: self, stack: vec![index] }
+    }
+}
+
+pub struct Ancestors<'a> {
+    entities: &'a Entites,
+    current: usize,
+}
+
+impl Iterator for Ancestors<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parent = self.entities.parent(self.current)?;
+        self.current = parent;
+        Some(parent)
+    }
+}
+
+pub struct Descendants<'a> {
+    entities: &'a Entites,
+    queue: VecDeque<usize>,
+}
+
+impl Iterator for Descendants<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.queue.pop_front()?;
+        self.queue.extend(self.entities.children(index));
+        Some(index)
+    }
+}
+
+pub struct DepthFirst<'a> {
+    entities: &'a Entites,
+    stack: Vec<usize>,
+}
+
+impl Iterator for DepthFirst<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.stack.pop()?;
+        self.stack.extend(self.entities.children(index).into_iter().rev());
+        Some(index)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn set_parent_keeps_both_sides_in_sync() -> Result<()> {
+        let mut entities = initialize_entities(3)?;
+        entities.set_parent(1, 0)?;
+        entities.set_parent(2, 0)?;
+
+        assert_eq!(entities.parent(1), Some(0));
+        assert_eq!(entities.children(0), vec![1, 2]);
+
+        entities.set_parent(2, 1)?;
+        assert_eq!(entities.children(0), vec![1]);
+        assert_eq!(entities.children(1), vec![2]);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_last_child_removes_children_component() -> Result<()> {
+        let mut entities = initialize_entities(2)?;
+        entities.add_child(0, 1)?;
+        entities.remove_child(0, 1)?;
+
+        assert_eq!(entities.parent(1), None);
+        assert!(entities.component_ref::<Children>(0).is_none());
+        assert!(entities.remove_child(0, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn cycles_are_rejected() -> Result<()> {
+        let mut entities = initialize_entities(3)?;
+        entities.set_parent(1, 0)?;
+        entities.set_parent(2, 1)?;
+
+        assert!(matches!(entities.set_parent(0, 2), Err(Error::InvalidHierarchy(_))));
+        assert!(matches!(entities.set_parent(0, 0), Err(Error::InvalidHierarchy(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn deleted_entities_cannot_join_the_hierarchy() -> Result<()> {
+        let mut entities = initialize_entities(2)?;
+        entities.delete_entity_by_id(1)?;
+
+        assert!(matches!(entities.set_parent(1, 0), Err(Error::EntityDoesNotExist(_))));
+        assert!(matches!(entities.set_parent(0, 1), Err(Error::EntityDoesNotExist(_))));
+        assert!(entities.entity_handle(1).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn traversal_orders() -> Result<()> {
+        // 0 -> (1 -> 3), 2
+        let mut entities = initialize_entities(4)?;
+        entities.add_child(0, 1)?;
+        entities.add_child(0, 2)?;
+        entities.add_child(1, 3)?;
+
+        assert_eq!(entities.ancestors(3).collect::<Vec<_>>(), vec![1, 0]);
+        assert_eq!(entities.descendants(0).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(entities.depth_first(0).collect::<Vec<_>>(), vec![0, 1, 3, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn deleting_an_entity_deletes_its_subtree() -> Result<()> {
+        let mut entities = initialize_entities(4)?;
+        entities.add_child(0, 1)?;
+        entities.add_child(1, 2)?;
+        entities.add_child(0, 3)?;
+
+        entities.delete_entity_by_id(1)?;
+
+        assert_eq!(entities.map[1], 0);
+        assert_eq!(entities.map[2], 0);
+        assert_eq!(entities.children(0), vec![3]);
+        Ok(())
+    }
+
+    fn initialize_entities(count: usize) -> Result<Entites> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        for index in 0..count {
+            entities.create_entity().with_component(index as u32)?;
+        }
+        Ok(entities)
+    }
+}
@@ -0,0 +1,329 @@
+use std::any::{Any, TypeId};
+use std::cell::{RefCell, RefMut};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::RangeBounds;
+
+use super::handle::Entity;
+use super::Entites;
+use crate::entity::error::{Error, Result};
+
+/// Which lookups an index answers. Both support equality,
+/// only `Ordered` supports ranges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexKind {
+    Hash,
+    Ordered,
+}
+
+/// Maps component values to the entities holding them.
+pub(crate) trait ComponentIndex: Debug {
+    /// Stores `value` for `index`, replacing what was stored before.
+    fn insert(&mut self, index: usize, value: &dyn Any);
+    fn remove(&mut self, index: usize);
+    fn get(&self, key: &dyn Any) -> Vec<usize>;
+    fn clear(&mut self);
+    fn as_any(&self) -> &dyn Any;
+}
+
+#[derive(Debug)]
+struct HashIndex<T> {
+    values: HashMap<usize, T>,
+    entities: HashMap<T, BTreeSet<usize>>,
+}
+
+#[derive(Debug)]
+struct OrderedIndex<T> {
+    values: HashMap<usize, T>,
+    entities: BTreeMap<T, BTreeSet<usize>>,
+}
+
+impl<T: Any + Debug + Hash + Eq + Clone> ComponentIndex for HashIndex<T> {
+    fn insert(&mut self, index: usize, value: &dyn Any) {
+        self.remove(index);
+        let value = value.downcast_ref::<T>().unwrap().clone();
+        self.entities.entry(value.clone()).or_default().insert(index);
+        self.values.insert(index, value);
+    }
+
+    fn remove(&mut self, index: usize) {
+        if let Some(value) = self.values.remove(&index) {
+            let entities = self.entities.get_mut(&value).unwrap();
+            entities.remove(&index);
+            if entities.is_empty() {
+                self.entities.remove(&value);
+            }
+        }
+    }
+
+    fn get(&self, key: &dyn Any) -> Vec<usize> {
+        key.downcast_ref::<T>()
+            .and_then(|key| self.entities.get(key))
+            .map(|entities| entities.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.entities.clear();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<T: Any + Debug + Ord + Clone> ComponentIndex for OrderedIndex<T> {
+    fn insert(&mut self, index: usize, value: &dyn Any) {
+        self.remove(index);
+        let value = value.downcast_ref::<T>().unwrap().clone();
+        self.entities.entry(value.clone()).or_default().insert(index);
+        self.values.insert(index, value);
+    }
+
+    fn remove(&mut self, index: usize) {
+        if let Some(value) = self.values.remove(&index) {
+            let entities = self.entities.get_mut(&value).unwrap();
+            entities.remove(&index);
+            if entities.is_empty() {
+                self.entities.remove(&value);
+            }
+        }
+    }
+
+    fn get(&self, key: &dyn Any) -> Vec<usize> {
+        key.downcast_ref::<T>()
+            .and_then(|key| self.entities.get(key))
+            .map(|entities| entities.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.entities.clear();
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// An index and the entities whose value may have changed through `get_mut`
+/// since it was last brought up to date.
+#[derive(Debug)]
+pub(crate) struct IndexEntry {
+    index: Box<dyn ComponentIndex>,
+    dirty: BTreeSet<usize>,
+}
+
+impl Entites {
+    /// Keeps a hash index of the values of `T` for `lookup`.
+    pub fn add_hash_index<T: Any + Debug + Hash + Eq + Clone>(&mut self) -> Result<()> {
+        self.add_index::<T>(Box::new(HashIndex::<T> { values: HashMap::new(), entities: HashMap::new() }))
+    }
+
+    /// Keeps an ordered index of the values of `T` for `lookup` and `lookup_range`.
+    pub fn add_ordered_index<T: Any + Debug + Ord + Clone>(&mut self) -> Result<()> {
+        self.add_index::<T>(Box::new(OrderedIndex::<T> { values: HashMap::new(), entities: BTreeMap::new() }))
+    }
+
+    fn add_index<T: Any>(&mut self, index: Box<dyn ComponentIndex>) -> Result<()> {
+        let type_id = TypeId::of::<T>();
+        if self.get_bitmask(&type_id).is_none() {
+            return Err(Error::ComponetNotRegister(format!("{} is not registered", std::any::type_name::<T>())));
+        }
+        let dirty = (0..self.map.len()).collect();
+        self.indexes.insert(type_id, RefCell::new(IndexEntry { index, dirty }));
+        Ok(())
+    }
+
+    pub fn index_kind<T: Any>(&self) -> Option<IndexKind> {
+        let entry = self.indexes.get(&TypeId::of::<T>())?.borrow();
+        if entry.index.as_any().is::<OrderedIndex<T>>() {
+            Some(IndexKind::Ordered)
+        } else {
+            Some(IndexKind::Hash)
+        }
+    }
+
+    /// Entities whose `T` equals `key`, in index order.
+    pub fn lookup<T: Any>(&self, key: &T) -> Result<Vec<Entity>> {
+        let entry = self.updated_index::<T>()?;
+        Ok(self.handles(entry.index.get(key)))
+    }
+
+    /// Entities whose `T` falls inside `range`, ordered by value. Needs an ordered index.
+    pub fn lookup_range<T: Any + Ord>(&self, range: impl RangeBounds<T>) -> Result<Vec<Entity>> {
+        let entry = self.updated_index::<T>()?;
+        let index = entry.index.as_any().downcast_ref::<OrderedIndex<T>>().ok_or_else(|| {
+            Error::MissingIndex(format!("{} has no ordered index", std::any::type_name::<T>()))
+        })?;
+        let indexes = index.entities.range(range).flat_map(|(_value, entities)| entities.iter().copied());
+        Ok(self.handles(indexes.collect()))
+    }
+
+    /// Rebuilds the index of `T`. Needed after changing `T` through components
+    /// returned by a query, which the index cannot see.
+    pub fn reindex<T: Any>(&mut self) -> Result<()> {
+        let entry = self.indexes.get_mut(&TypeId::of::<T>()).ok_or_else(|| missing_index::<T>())?;
+        entry.get_mut().dirty = (0..self.map.len()).collect();
+        Ok(())
+    }
+
+    /// Fails with `ComponentAlreadyBorrowed`, leaving the index as it was,
+    /// when a value it has to read is borrowed mutably elsewhere.
+    fn updated_index<T: Any>(&self) -> Result<RefMut<'_, IndexEntry>> {
+        let type_id = TypeId::of::<T>();
+        let mut entry = self.indexes.get(&type_id).ok_or_else(|| missing_index::<T>())?.try_borrow_mut().map_err(
+            |_| Error::ComponentAlreadyBorrowed(format!("the index of {} is in use", std::any::type_name::<T>()))
+        )?;
+        let mask = self.get_bitmask(&type_id).unwrap();
+        let IndexEntry { index, dirty } = &mut *entry;
+        let mut values = vec![];
+        for entity in dirty.iter().copied() {
+            let value = match self.component_at(&type_id, entity) {
+                Some(component) if self.contains_component(entity, mask) => Some(component.try_borrow().map_err(
+                    |_| Error::ComponentAlreadyBorrowed(format!("{} of entity {entity} is already borrowed", std::any::type_name::<T>()))
+                )?),
+                _ => None,
+            };
+            values.push((entity, value));
+        }
+        for (entity, value) in values {
+            match value {
+                Some(value) => index.insert(entity, &*value),
+                None => index.remove(entity),
+            }
+        }
+        dirty.clear();
+        Ok(entry)
+    }
+
+    fn handles(&self, indexes: Vec<usize>) -> Vec<Entity> {
+        indexes.into_iter().filter_map(|index| self.entity_handle(index)).collect()
+    }
+
+    /// Called for every component stored, so a new value shows up right away.
+    pub(crate) fn index_inserted(&self, type_id: &TypeId, index: usize, value: &dyn Any) {
+        if let Some(entry) = self.indexes.get(type_id) {
+            let mut entry = entry.borrow_mut();
+            entry.dirty.remove(&index);
+            entry.index.insert(index, value);
+        }
+    }
+
+    /// `get_mut` hands out a `RefMut`, so the new value is only read on the next lookup.
+    pub(crate) fn index_mutated(&mut self, type_id: &TypeId, index: usize) {
+        if let Some(entry) = self.indexes.get_mut(type_id) {
+            entry.get_mut().dirty.insert(index);
+        }
+        self.relation_mutated(type_id, index);
+    }
+
+    /// Drops `index` from the indexes of every component in `removed`.
+    pub(crate) fn index_removed(&mut self, index: usize, removed: u32) {
+        for (type_id, entry) in self.indexes.iter_mut() {
+            if self.bit_masks.get(type_id).is_some_and(|mask| removed & mask != 0) {
+                let entry = entry.get_mut();
+                entry.dirty.remove(&index);
+                entry.index.remove(index);
+            }
+        }
+    }
+
+    pub(crate) fn clear_indexes(&mut self) {
+        for entry in self.indexes.values_mut() {
+            let entry = entry.get_mut();
+            entry.dirty.clear();
+            entry.index.clear();
+        }
+    }
+}
+
+fn missing_index<T: Any>() -> Error {
+    Error::MissingIndex(format!("{} has no index", std::any::type_name::<T>()))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::query::Query;
+    use anyhow::Result;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    struct NetworkId(u32);
+
+    fn indexes(entities: &[Entity]) -> Vec<usize> {
+        entities.iter().map(Entity::index).collect()
+    }
+
+    fn initialize_entities() -> Result<Entites> {
+        let mut entities = Entites::default();
+        entities.register_component::<NetworkId>();
+        entities.register_component::<f32>();
+        for id in [42, 7, 42, 13] {
+            entities.create_entity().with_component(NetworkId(id))?;
+        }
+        Ok(entities)
+    }
+
+    #[test]
+    fn existing_and_new_values_are_indexed() -> Result<()> {
+        let mut entities = initialize_entities()?;
+        entities.add_hash_index::<NetworkId>()?;
+        entities.create_entity().with_component(NetworkId(7))?;
+
+        assert_eq!(indexes(&entities.lookup(&NetworkId(42))?), vec![0, 2]);
+        assert_eq!(indexes(&entities.lookup(&NetworkId(7))?), vec![1, 4]);
+        assert!(entities.lookup(&NetworkId(99))?.is_empty());
+        assert_eq!(entities.index_kind::<NetworkId>(), Some(IndexKind::Hash));
+        Ok(())
+    }
+
+    #[test]
+    fn index_follows_mutation_and_deletion() -> Result<()> {
+        let mut entities = initialize_entities()?;
+        entities.add_hash_index::<NetworkId>()?;
+
+        entities.get_mut::<NetworkId>(0).unwrap().0 = 7;
+        entities.delete_component_by_entity_id::<NetworkId>(1)?;
+        entities.delete_entity_by_id(2)?;
+
+        assert!(entities.lookup(&NetworkId(42))?.is_empty());
+        assert_eq!(indexes(&entities.lookup(&NetworkId(7))?), vec![0]);
+        Ok(())
+    }
+
+    #[test]
+    fn ordered_index_answers_ranges() -> Result<()> {
+        let mut entities = initialize_entities()?;
+        entities.add_ordered_index::<NetworkId>()?;
+
+        assert_eq!(indexes(&entities.lookup_range(NetworkId(10)..)?), vec![3, 0, 2]);
+        assert_eq!(indexes(&entities.lookup_range(..=NetworkId(13))?), vec![1, 3]);
+
+        entities.add_hash_index::<NetworkId>()?;
+        assert!(matches!(entities.lookup_range(NetworkId(0)..), Err(Error::MissingIndex(_))));
+        assert!(matches!(entities.lookup(&1.0_f32), Err(Error::MissingIndex(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn lookup_reports_values_borrowed_elsewhere() -> Result<()> {
+        let mut entities = initialize_entities()?;
+        entities.add_hash_index::<NetworkId>()?;
+        let (_indexes, components) = Query::new(&entities).with_component::<NetworkId>()?.run();
+        entities.reindex::<NetworkId>()?;
+
+        let mut borrowed = components[0][1].borrow_mut();
+        assert!(matches!(entities.lookup(&NetworkId(7)), Err(Error::ComponentAlreadyBorrowed(_))));
+        borrowed.downcast_mut::<NetworkId>().unwrap().0 = 8;
+        drop(borrowed);
+
+        assert_eq!(indexes(&entities.lookup(&NetworkId(8))?), vec![1]);
+        assert!(entities.lookup(&NetworkId(7))?.is_empty());
+        Ok(())
+    }
+}
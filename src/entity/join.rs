@@ -0,0 +1,152 @@
+use std::any::{Any, TypeId};
+
+use super::handle::Entity;
+use super::query::Query;
+use super::Component;
+use crate::entity::error::{Error, Result};
+
+/// A component that points at another entity, e.g. `Target(Entity)`.
+pub trait EntityReference {
+    fn target(&self) -> Entity;
+}
+
+impl EntityReference for Entity {
+    fn target(&self) -> Entity {
+        *self
+    }
+}
+
+/// What a join found on the referenced entity.
+#[derive(Debug, Clone)]
+pub enum JoinTarget {
+    Found(Entity, Component),
+    /// The target is alive but has no component of the joined type.
+    MissingComponent(Entity),
+    /// The target was deleted, or its slot now holds another entity.
+    Deleted(Entity),
+}
+
+impl JoinTarget {
+    pub fn entity(&self) -> Entity {
+        match self {
+            Self::Found(entity, _) | Self::MissingComponent(entity) | Self::Deleted(entity) => *entity,
+        }
+    }
+
+    pub fn component(&self) -> Option<&Component> {
+        match self {
+            Self::Found(_, component) => Some(component),
+            _ => None,
+        }
+    }
+}
+
+/// One row per matching entity: its handle, the query's components and the join result.
+pub type JoinItems = Vec<(Entity, Vec<Component>, JoinTarget)>;
+
+impl Query<'_> {
+    /// Follows the `R` reference of every matching entity and fetches `T` from its target.
+    /// `R` has to be part of the query.
+    pub fn join<R: EntityReference + Any, T: Any>(&self) -> Result<JoinItems> {
+        let column = self.column_of::<R>()?;
+        let entities = self.entities();
+        let type_id = TypeId::of::<T>();
+        let mask = entities.get_bitmask(&type_id).ok_or_else(|| {
+            Error::ComponetNotRegister(format!("{} is not registered", std::any::type_name::<T>()))
+        })?;
+
+        Ok(self.run_entities()
+            .into_iter()
+            .map(|(entity, components)| {
+                let target = components[column].borrow().downcast_ref::<R>().unwrap().target();
+                let joined = if !entities.is_alive(target) {
+                    JoinTarget::Deleted(target)
+                } else if entities.has_component(target.index(), mask) {
+                    JoinTarget::Found(target, entities.component_at(&type_id, target.index()).unwrap().clone())
+                } else {
+                    JoinTarget::MissingComponent(target)
+                };
+                (entity, components, joined)
+            })
+            .collect())
+    }
+
+    /// Like `join` but drops entities whose target is gone or lacks `T`.
+    pub fn inner_join<R: EntityReference + Any, T: Any>(&self) -> Result<Vec<(Entity, Vec<Component>, Component)>> {
+        Ok(self.join::<R, T>()?
+            .into_iter()
+            .filter_map(|(entity, components, joined)| match joined {
+                JoinTarget::Found(_, component) => Some((entity, components, component)),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::Entites;
+    use anyhow::Result;
+
+    struct Target(Entity);
+
+    impl EntityReference for Target {
+        fn target(&self) -> Entity {
+            self.0
+        }
+    }
+
+    fn position(joined: &JoinTarget) -> Option<u32> {
+        joined.component().map(|component| *component.borrow().downcast_ref::<u32>().unwrap())
+    }
+
+    #[test]
+    fn join_reports_found_missing_and_deleted_targets() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        entities.register_component::<Target>();
+
+        entities.create_entity().with_component(7_u32)?;
+        let with_position = entities.current_entity().unwrap();
+        entities.create_entity().with_component(1.0_f32)?;
+        let without_position = entities.current_entity().unwrap();
+        entities.create_entity().with_component(8_u32)?;
+        let deleted = entities.current_entity().unwrap();
+
+        for target in [with_position, without_position, deleted] {
+            entities.create_entity().with_component(Target(target))?;
+        }
+        entities.delete_entity_by_id(deleted.index())?;
+        entities.create_entity().with_component(9_u32)?;
+
+        let mut query = Query::new(&entities);
+        query.with_component::<Target>()?;
+        let joined = query.join::<Target, u32>()?;
+
+        assert_eq!(joined.len(), 3);
+        assert_eq!(position(&joined[0].2), Some(7));
+        assert!(matches!(joined[1].2, JoinTarget::MissingComponent(entity) if entity == without_position));
+        assert!(matches!(joined[2].2, JoinTarget::Deleted(entity) if entity == deleted));
+        assert_eq!(query.inner_join::<Target, u32>()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn join_needs_the_reference_in_the_query() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<Entity>();
+        entities.create_entity().with_component(1_u32)?;
+
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?;
+
+        assert!(matches!(query.join::<Entity, u32>(), Err(Error::ComponentNotFound(_))));
+        query.with_component::<Entity>()?;
+        assert!(matches!(query.join::<Entity, bool>(), Err(Error::ComponetNotRegister(_))));
+        Ok(())
+    }
+}
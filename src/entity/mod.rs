@@ -1,12 +1,41 @@
 use std::any::{Any, TypeId};
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 
 pub mod query;
+pub mod snapshot;
+pub mod dynamic;
+pub mod hierarchy;
+pub mod relation;
+pub mod sparse_set;
+pub mod entity_ref;
+pub mod archetype;
+pub mod query_state;
+pub mod parallel;
+pub mod handle;
+pub mod sort;
+pub mod join;
+pub mod index;
+pub mod unique;
+pub mod name;
+pub mod change;
+pub mod transfer;
+pub mod prefab;
+pub mod rollback;
+pub mod delta;
 mod error;
 pub use self::error::{Error,Result};
+use self::snapshot::Codec;
+use self::delta::EqFn;
+use self::dynamic::ComponentId;
+use self::sparse_set::SparseSet;
+use self::archetype::Archetype;
+use self::index::IndexEntry;
+use self::change::ChangeTicks;
+use self::transfer::TransferFns;
+use self::relation::RelationSources;
 
 pub type Component = Rc<RefCell<dyn Any>>;
 pub type Components = HashMap<TypeId, Vec<Option<Component>>>;
@@ -15,55 +44,197 @@ pub type Components = HashMap<TypeId, Vec<Option<Component>>>;
 //pub use entity::Query;
 //use self::query::Query;
 
-#[derive(Default, Debug)]
+/// How the values of a component are stored.
+/// `Table` keeps one slot per entity, `SparseSet` only stores the entities
+/// that have the component and is cheaper to add to and remove from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageType {
+    #[default]
+    Table,
+    SparseSet,
+}
+
+#[derive(Default)]
 pub struct Entites {
     components: Components,
     bit_masks:HashMap<TypeId, u32>,
     map: Vec<u32>,
     inserting_into_index: usize,
+    codecs: HashMap<TypeId, Codec>,
+    dynamic_components: HashMap<ComponentId, Vec<Option<Component>>>,
+    dynamic_bit_masks: HashMap<ComponentId, u32>,
+    dynamic_names: Vec<String>,
+    relation_cleanups: HashMap<TypeId, fn(&mut Entites, usize)>,
+    relation_sources: HashMap<TypeId, RelationSources>,
+    sparse_sets: HashMap<TypeId, SparseSet>,
+    tags: HashMap<TypeId, Option<Component>>,
+    archetypes: Vec<Archetype>,
+    archetype_lookup: HashMap<u32, usize>,
+    generations: Vec<u32>,
+    indexes: HashMap<TypeId, RefCell<IndexEntry>>,
+    unique: HashSet<TypeId>,
+    changes: ChangeTicks,
+    transfer_fns: HashMap<TypeId, TransferFns>,
+    comparisons: HashMap<TypeId, EqFn>,
 }
 
 impl Entites {
     pub fn register_component<T:Any + 'static>(&mut self) {
+        self.register_component_with_storage::<T>(StorageType::Table);
+    }
+
+    /// Registering an already registered component keeps its original storage.
+    /// Zero-sized components ignore `storage`: they are tags that only live
+    /// as a bit in the entity map and share one value between all entities.
+    ///
+    /// Panics when all bits of the entity map are taken,
+    /// use `try_register_component_with_storage` to get an error instead.
+    pub fn register_component_with_storage<T:Any + 'static>(&mut self, storage: StorageType) {
+        if let Err(error) = self.try_register_component_with_storage::<T>(storage) {
+            panic!("could not register {}: {error}", std::any::type_name::<T>());
+        }
+    }
+
+    /// Same as `register_component_with_storage`, but fails with `TooManyComponents`
+    /// once all bits of the entity map are taken.
+    pub fn try_register_component_with_storage<T:Any + 'static>(&mut self, storage: StorageType) -> Result<()> {
         let type_id = TypeId::of::<T>();
-        let bit_mask = 2_u32.pow(self.bit_masks.len() as u32);
-        self.components.insert(type_id, vec![]);
+        if self.bit_masks.contains_key(&type_id) {
+            return Ok(());
+        }
+        let bit_mask = self.next_bit_mask()?;
         self.bit_masks.insert(type_id, bit_mask);
+        self.transfer_fns.insert(type_id, TransferFns::of::<T>());
+        if std::mem::size_of::<T>() == 0 {
+            self.tags.insert(type_id, None);
+            return Ok(());
+        }
+        match storage {
+            StorageType::Table => {
+                self.components.insert(type_id, vec![None; self.map.len()]);
+            }
+            StorageType::SparseSet => {
+                self.sparse_sets.insert(type_id, SparseSet::default());
+            }
+        }
+        if type_id == TypeId::of::<name::Name>() {
+            self.prepare_names()?;
+        }
+        Ok(())
+    }
+
+    pub fn is_tag(&self, type_id:&TypeId) -> bool {
+        self.tags.contains_key(type_id)
+    }
+
+    /// `None` for unregistered components and for tags.
+    pub fn storage_type(&self, type_id:&TypeId) -> Option<StorageType> {
+        if self.sparse_sets.contains_key(type_id) {
+            Some(StorageType::SparseSet)
+        } else if self.components.contains_key(type_id) {
+            Some(StorageType::Table)
+        } else {
+            None
+        }
+    }
+
+    /// Static and dynamic components share the bits of the entity map.
+    fn next_bit_mask(&self) -> Result<u32> {
+        let taken = self.bit_masks.len() + self.dynamic_bit_masks.len();
+        if taken >= u32::BITS as usize {
+            return Err(Error::TooManyComponents(format!("all {} component bits are taken", u32::BITS)));
+        }
+        Ok(1 << taken)
     }
 
     pub fn create_entity(&mut self) -> &mut Self {
         if let Some((index, _)) = self.map.iter().enumerate().find(|(_index, mask)| **mask == 0){
             self.inserting_into_index = index;
         } else {
-            self.components
-                .iter_mut()
-                .for_each(|(_key, componets)| componets.push(None));
-            self.map.push(0);
+            self.push_slot();
             self.inserting_into_index = self.map.len() - 1;
         }
         self
     }
 
+    /// Appends an empty entity slot.
+    fn push_slot(&mut self) {
+        self.components
+            .iter_mut()
+            .for_each(|(_key, componets)| componets.push(None));
+        self.dynamic_components
+            .iter_mut()
+            .for_each(|(_key, componets)| componets.push(None));
+        self.map.push(0);
+        self.generations.push(0);
+    }
+
+    /// Fails with `ComponetNotRegister` for unregistered components,
+    /// which would otherwise be dropped without a trace.
     pub fn with_component(&mut self, data:impl Any) -> Result<&mut Self>{
         let type_id = data.type_id();
         //let map_index = self.map.len() -1 ;
         let index = self.inserting_into_index;
-        if let Some(components) = self.components.get_mut(&type_id) {
-            let component = components
-                .get_mut(index)
-                .ok_or_else(
-                    || Error::ComponentNotFound("component not created using entity creation".to_string())
-                )?;
-            *component = Some(Rc::new(RefCell::new(data)));
+        if !self.bit_masks.contains_key(&type_id) {
+            return Err(
+                Error::ComponetNotRegister("try to insert data for component that wasn't registerd".to_string())
+            );
+        }
+        if index >= self.map.len() {
+            return Err(
+                Error::ComponentNotFound("component not created using entity creation".to_string())
+            );
+        }
+        self.insert_value(index, data)?;
+        
+        Ok(self)
+    }
+
+    /// Tags reuse their shared value so inserting them never allocates.
+    fn insert_value<T:Any>(&mut self, index:usize, data:T) -> Result<()> {
+        let type_id = TypeId::of::<T>();
+        let component = match self.tags.get(&type_id) {
+            Some(Some(shared)) => shared.clone(),
+            _ => Rc::new(RefCell::new(data)),
+        };
+        self.insert_component(type_id, index, component)
+    }
 
-            let bit_mask = self.bit_masks.get(&type_id).unwrap();
-            self.map[index] |= *bit_mask;
+    /// Stores the component in whichever storage it was registered with.
+    pub(crate) fn insert_component(&mut self, type_id:TypeId, index:usize, component:Component) -> Result<()> {
+        let bit_mask = self.get_bitmask(&type_id).ok_or_else(
+            || Error::ComponetNotRegister("attempting use component that wasn't registerd".to_string())
+        )?;
+        if index >= self.map.len() {
+            return Err(Error::EntityDoesNotExist("attempting to add a component to an entity that does not exist".to_string()));
+        }
+        self.check_unique(&type_id, index, bit_mask)?;
+        if let Some(shared) = self.tags.get_mut(&type_id) {
+            shared.get_or_insert(component);
+        } else if let Some(sparse_set) = self.sparse_sets.get_mut(&type_id) {
+            sparse_set.insert(index, component);
+        } else if let Some(components) = self.components.get_mut(&type_id) {
+            components[index] = Some(component);
+        }
+        if let Some(component) = self.component_at(&type_id, index) {
+            self.index_inserted(&type_id, index, &*component.borrow());
+        }
+        self.relation_inserted(&type_id, index);
+        self.record_change(&type_id, index);
+        self.add_to_mask(index, bit_mask);
+
+        Ok(())
+    }
 
+    /// The stored value, regardless of storage type. Does not look at the entity map.
+    fn component_at(&self, type_id:&TypeId, index:usize) -> Option<&Component> {
+        if let Some(shared) = self.tags.get(type_id) {
+            shared.as_ref()
+        } else if let Some(sparse_set) = self.sparse_sets.get(type_id) {
+            sparse_set.get(index)
         } else {
-            Error::ComponetNotRegister("try to insert data for component that wasn't registerd".to_string());
+            self.components.get(type_id)?.get(index)?.as_ref()
         }
-        
-        Ok(self)
     }
 
     pub fn get_bitmask(&self, type_id:&TypeId) -> Option<u32> {
@@ -74,48 +245,127 @@ impl Entites {
         self.map[index] & mask == mask
     }
 
+    /// Like `has_component` but false for indexes that were never created.
+    fn contains_component(&self, index: usize, mask: u32) -> bool {
+        self.map.get(index).is_some_and(|entity_map| entity_map & mask == mask)
+    }
+
     pub fn delete_component_by_entity_id<T:Any>(&mut self, index:usize) -> Result<()> {
-        let type_id = TypeId::of::<T>();
+        self.delete_component(TypeId::of::<T>(), index)
+    }
+
+    pub(crate) fn delete_component(&mut self, type_id:TypeId, index:usize) -> Result<()> {
         let mask = if let Some(mask) = self.bit_masks.get(&type_id){
-            mask
+            *mask
         } else {
             return Err(
                 Error::ComponetNotRegister("attempting use component that wasn't registerd".to_string())
             );
         };
-        if self.has_component(index, *mask) {
-            self.map[index] ^= *mask;
+        if self.has_component(index, mask) {
+            self.remove_from_mask(index, mask);
+        }
+        if let Some(sparse_set) = self.sparse_sets.get_mut(&type_id) {
+            sparse_set.remove(index);
+        }
+        if let Some(slot) = self.components.get_mut(&type_id).and_then(|components| components.get_mut(index)) {
+            *slot = None;
         }
 
         Ok(())
     }
 
     pub fn add_component_to_entity_by_id(&mut self, data: impl Any, index:usize) -> Result<()> {
-        let type_id = data.type_id();
-        let mask = if let Some(mask) = self.bit_masks.get(&type_id){
-            mask
+        self.insert_value(index, data)
+    }
+
+    pub(crate) fn ensure_not_tag<T:Any>(&self) -> Result<()> {
+        if self.is_tag(&TypeId::of::<T>()) {
+            return Err(Error::TagNotMutable(format!("{} is a tag and has no value to change", std::any::type_name::<T>())));
+        }
+        Ok(())
+    }
+
+    /// Deleted slots and slots without any component do not count as entities.
+    fn ensure_exists(&self, index: usize) -> Result<()> {
+        if self.entity_handle(index).is_some() {
+            Ok(())
         } else {
-            return Err(
-                Error::ComponetNotRegister("attempting use component that wasn't registerd".to_string())
-            );
-        };
+            Err(Error::EntityDoesNotExist(format!("entity {index} does not exist")))
+        }
+    }
 
-        self.map[index] |= *mask;
-        let components = self.components.get_mut(&type_id).unwrap();
-        components[index] = Some(Rc::new(RefCell::new(data)));
+    fn component_ref<T:Any>(&self, index:usize) -> Option<Ref<'_, T>> {
+        let mask = self.get_bitmask(&TypeId::of::<T>())?;
+        if !self.contains_component(index, mask) {
+            return None;
+        }
+        let component = self.component_at(&TypeId::of::<T>(), index)?;
+        Ref::filter_map(component.borrow(), |data| data.downcast_ref::<T>()).ok()
+    }
 
-        Ok(())
+    /// `None` for tags: all entities share their value, so it is never borrowed mutably.
+    fn component_mut<T:Any>(&self, index:usize) -> Option<RefMut<'_, T>> {
+        if self.is_tag(&TypeId::of::<T>()) {
+            return None;
+        }
+        let mask = self.get_bitmask(&TypeId::of::<T>())?;
+        if !self.contains_component(index, mask) {
+            return None;
+        }
+        let component = self.component_at(&TypeId::of::<T>(), index)?;
+        self.record_change(&TypeId::of::<T>(), index);
+        RefMut::filter_map(component.borrow_mut(), |data| data.downcast_mut::<T>()).ok()
+    }
+
+    /// Every component currently attached to the entity, in no particular order.
+    pub fn components_of(&self, index:usize) -> Result<Vec<(TypeId, Component)>> {
+        let entity_map = self.map.get(index).ok_or_else(
+            || Error::EntityDoesNotExist("attempting to read an entity that does not exist".to_string())
+        )?;
+        let components = self.bit_masks
+            .iter()
+            .filter(|(_type_id, mask)| entity_map & **mask == **mask)
+            .filter_map(|(type_id, _mask)| {
+                let component = self.component_at(type_id, index)?;
+                Some((*type_id, component.clone()))
+            })
+            .collect();
+        Ok(components)
     }
 
+    /// Deletes the entity together with all of its descendants.
+    /// Relations targeting any of the deleted entities are removed.
     pub fn delete_entity_by_id(&mut self, index:usize) -> Result<()> {
-        if let Some(map) = self.map.get_mut(index) {
-            *map = 0;
-        } else {
+        if index >= self.map.len() {
             return Err(Error::EntityDoesNotExist("attemting to delete an entity does not exits".to_string()));
         }
+        if let Some(parent) = self.parent(index) {
+            self.remove_child(parent, index)?;
+        }
+        let subtree = self.depth_first(index).collect::<Vec<_>>();
+        for entity in subtree {
+            self.set_mask(entity, 0);
+            self.release_components(entity);
+            self.remove_relations_targeting(entity);
+        }
        
         Ok(())
     }
+
+    /// Drops the component values of a deleted entity right away
+    /// instead of when its slot gets reused.
+    fn release_components(&mut self, index:usize) {
+        for sparse_set in self.sparse_sets.values_mut() {
+            sparse_set.remove(index);
+        }
+        for components in self.components.values_mut() {
+            components[index] = None;
+        }
+        for components in self.dynamic_components.values_mut() {
+            components[index] = None;
+        }
+    }
 }
 
 
@@ -196,6 +446,15 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn with_component_rejects_unregistered_components() {
+        let mut entities = Entites::default();
+        entities.register_component::<Health>();
+        let result = entities.create_entity().with_component(Speed(25.0));
+
+        assert!(matches!(result, Err(Error::ComponetNotRegister(_))));
+    }
+
     #[test]
     fn map_is_updated_when_creating_entities() -> Result<()>{
         let mut entities = Entites::default();
@@ -276,6 +535,54 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn sparse_set_components_are_removed_from_their_set() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<Health>();
+        entities.register_component_with_storage::<Speed>(StorageType::SparseSet);
+        entities.create_entity()
+            .with_component(Health(100))?
+            .with_component(Speed(25.0))?;
+        entities.create_entity()
+            .with_component(Health(50))?
+            .with_component(Speed(10.0))?;
+
+        assert!(!entities.components.contains_key(&TypeId::of::<Speed>()));
+        assert_eq!(entities.map[0], 3);
+
+        entities.delete_component_by_entity_id::<Speed>(0)?;
+        assert_eq!(entities.map[0], 1);
+        let speeds = &entities.sparse_sets[&TypeId::of::<Speed>()];
+        assert_eq!(speeds.entities(), &[1]);
+
+        entities.delete_entity_by_id(1)?;
+        assert!(entities.sparse_sets[&TypeId::of::<Speed>()].entities().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn tags_are_stored_in_the_map_only() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<Health>();
+        entities.register_component::<Player>();
+        entities.create_entity()
+            .with_component(Health(100))?
+            .with_component(Player)?;
+        entities.create_entity()
+            .with_component(Player)?;
+
+        assert!(entities.is_tag(&TypeId::of::<Player>()));
+        assert!(!entities.components.contains_key(&TypeId::of::<Player>()));
+        assert_eq!(entities.map, vec![3, 2]);
+        let first = entities.component_at(&TypeId::of::<Player>(), 0).unwrap();
+        let second = entities.component_at(&TypeId::of::<Player>(), 1).unwrap();
+        assert!(Rc::ptr_eq(first, second));
+
+        entities.delete_component_by_entity_id::<Player>(0)?;
+        assert_eq!(entities.map, vec![1, 2]);
+        Ok(())
+    }
+
     #[test]
     fn created_entitites_are_inserter_into_deleted_entities_column() -> Result<()> {
         let mut entities = Entites::default();
@@ -301,4 +608,5 @@ mod test {
 
     struct Health(pub u32);
     struct Speed(pub f32);
+    struct Player;
 }
\ No newline at end of file
@@ -0,0 +1,120 @@
+use std::cell::Ref;
+use std::fmt;
+
+use super::handle::Entity;
+use super::Entites;
+use crate::entity::error::Result;
+
+/// Human readable name of an entity, e.g. one a level designer gave it.
+/// Names are indexed, so `entity_by_name` does not scan the world.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Name(String);
+
+impl Name {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Entites {
+    /// Called when `Name` gets registered, however that happens.
+    pub(crate) fn prepare_names(&mut self) -> Result<()> {
+        self.add_hash_index::<Name>()?;
+        self.register_clone::<Name>();
+        Ok(())
+    }
+
+    /// Names the entity, replacing any name it had.
+    pub fn set_name(&mut self, index: usize, name: impl Into<String>) -> Result<()> {
+        self.ensure_exists(index)?;
+        self.register_component::<Name>();
+        self.insert_value(index, Name::new(name))
+    }
+
+    pub fn name(&self, index: usize) -> Option<Ref<'_, str>> {
+        self.component_ref::<Name>(index).map(|name| Ref::map(name, Name::as_str))
+    }
+
+    /// The entity called `name`. When several share it, the one with the lowest index.
+    pub fn entity_by_name(&self, name: &str) -> Option<Entity> {
+        self.lookup(&Name::new(name)).ok()?.into_iter().next()
+    }
+
+    /// The name of the entity, or `#index` for unnamed ones.
+    pub(crate) fn entity_label(&self, index: usize) -> String {
+        match self.name(index) {
+            Some(name) => name.to_string(),
+            None => format!("#{index}"),
+        }
+    }
+}
+
+impl fmt::Debug for Entites {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut entities = f.debug_map();
+        for index in self.iter_entities().map(|entity| entity.index()) {
+            entities.entry(&format_args!("{}", self.entity_label(index)), &format_args!("{:#b}", self.map[index]));
+        }
+        entities.finish()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn names_are_looked_up_and_renamed() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?;
+
+        assert!(entities.entity_by_name("door_01").is_none());
+        entities.set_name(1, "door_01")?;
+        assert_eq!(entities.entity_by_name("door_01").unwrap().index(), 1);
+
+        entities.set_name(1, "door_02")?;
+        assert!(entities.entity_by_name("door_01").is_none());
+        assert_eq!(&*entities.name(1).unwrap(), "door_02");
+
+        entities.delete_entity_by_id(1)?;
+        assert!(entities.entity_by_name("door_02").is_none());
+        assert!(entities.set_name(5, "missing").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn names_added_as_components_are_indexed() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<Name>();
+        entities.create_entity().with_component(Name::new("lamp"))?;
+
+        assert_eq!(entities.entity_by_name("lamp").unwrap().index(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn debug_output_uses_names() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?;
+        entities.set_name(0, "door_01")?;
+
+        assert_eq!(format!("{entities:?}"), "{door_01: 0b11, #1: 0b1}");
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefMut};
+use std::sync::Mutex;
+
+use super::query::Query;
+use crate::entity::error::{Error, Result};
+
+/// Splits `items` into batches and hands them to scoped worker threads,
+/// one batch at a time, until all of them are processed.
+fn run_batches<I: Send>(items: &mut [I], batch_size: usize, f: impl Fn(&mut I) + Sync) {
+    let batch_size = batch_size.max(1);
+    let batch_count = items.len().div_ceil(batch_size);
+    let workers = std::thread::available_parallelism()
+        .map(|workers| workers.get())
+        .unwrap_or(1)
+        .min(batch_count);
+    let batches = Mutex::new(items.chunks_mut(batch_size));
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let batch = batches.lock().unwrap().next();
+                match batch {
+                    Some(batch) => batch.iter_mut().for_each(&f),
+                    None => break,
+                }
+            });
+        }
+    });
+}
+
+impl Query<'_> {
+    /// Calls `f` with every matching entity's `T`, spread over a thread pool
+    /// in batches of `batch_size` entities.
+    pub fn par_for_each<T: Any + Sync>(&self, batch_size: usize, f: impl Fn(usize, &T) + Sync) -> Result<()> {
+        let column = self.column_of::<T>()?;
+        let (indexes, components) = self.run();
+        let guards = components[column]
+            .iter()
+            .map(|component| component.try_borrow().map_err(|_| borrowed::<T>()))
+            .collect::<Result<Vec<Ref<dyn Any>>>>()?;
+        let mut items = indexes
+            .iter()
+            .zip(&guards)
+            .map(|(index, guard)| (*index, guard.downcast_ref::<T>().unwrap()))
+            .collect::<Vec<_>>();
+
+        run_batches(&mut items, batch_size, |(index, data)| f(*index, data));
+        Ok(())
+    }
+
+    /// Like `par_for_each` but with mutable access. Every `T` is borrowed
+    /// mutably before any thread starts, so nothing else can observe it meanwhile.
+    /// Tags have no value to change and are rejected.
+    pub fn par_for_each_mut<T: Any + Send>(&self, batch_size: usize, f: impl Fn(usize, &mut T) + Sync) -> Result<()> {
+        self.entities().ensure_not_tag::<T>()?;
+        let column = self.column_of::<T>()?;
+        let (indexes, components) = self.run();
+        let mut guards = components[column]
+            .iter()
+            .map(|component| component.try_borrow_mut().map_err(|_| borrowed::<T>()))
+            .collect::<Result<Vec<RefMut<dyn Any>>>>()?;
+        let mut items = indexes
+            .iter()
+            .zip(&mut guards)
+            .map(|(index, guard)| (*index, guard.downcast_mut::<T>().unwrap()))
+            .collect::<Vec<_>>();
+
+        run_batches(&mut items, batch_size, |(index, data)| f(*index, data));
+        Ok(())
+    }
+
+    /// Mutates `M` while reading `R` of the same entity, e.g. moving particles by their velocity.
+    pub fn par_for_each_mut_with<M: Any + Send, R: Any + Sync>(
+        &self,
+        batch_size: usize,
+        f: impl Fn(usize, &mut M, &R) + Sync,
+    ) -> Result<()> {
+        if TypeId::of::<M>() == TypeId::of::<R>() {
+            return Err(borrowed::<M>());
+        }
+        self.entities().ensure_not_tag::<M>()?;
+        let mut_column = self.column_of::<M>()?;
+        let ref_column = self.column_of::<R>()?;
+        let (indexes, components) = self.run();
+        let mut mut_guards = components[mut_column]
+            .iter()
+            .map(|component| component.try_borrow_mut().map_err(|_| borrowed::<M>()))
+            .collect::<Result<Vec<RefMut<dyn Any>>>>()?;
+        let ref_guards = components[ref_column]
+            .iter()
+            .map(|component| component.try_borrow().map_err(|_| borrowed::<R>()))
+            .collect::<Result<Vec<Ref<dyn Any>>>>()?;
+        let mut items = indexes
+            .iter()
+            .zip(&mut mut_guards)
+            .zip(&ref_guards)
+            .map(|((index, mut_guard), ref_guard)| {
+                (*index, mut_guard.downcast_mut::<M>().unwrap(), ref_guard.downcast_ref::<R>().unwrap())
+            })
+            .collect::<Vec<_>>();
+
+        run_batches(&mut items, batch_size, |(index, data, other)| f(*index, data, other));
+        Ok(())
+    }
+}
+
+fn borrowed<T: Any>() -> Error {
+    Error::ComponentAlreadyBorrowed(format!("{} is already borrowed", std::any::type_name::<T>()))
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::entity::Entites;
+    use anyhow::Result;
+
+    fn initialize_entities(count: u32) -> Result<Entites> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        for value in 0..count {
+            entities.create_entity().with_component(value)?.with_component(1.5_f32)?;
+        }
+        Ok(entities)
+    }
+
+    #[test]
+    fn par_for_each_visits_every_entity() -> Result<()> {
+        let entities = initialize_entities(1000)?;
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?;
+
+        let sum = AtomicUsize::new(0);
+        query.par_for_each::<u32>(64, |_index, value| {
+            sum.fetch_add(*value as usize, Ordering::Relaxed);
+        })?;
+
+        assert_eq!(sum.into_inner(), (0..1000).sum::<usize>());
+        Ok(())
+    }
+
+    #[test]
+    fn par_for_each_mut_with_reads_other_component() -> Result<()> {
+        let entities = initialize_entities(100)?;
+        let mut query = Query::new(&entities);
+        query.with_component::<f32>()?.with_component::<u32>()?;
+
+        query.par_for_each_mut_with::<u32, f32>(7, |index, value, factor| {
+            *value = (index as f32 * factor) as u32;
+        })?;
+
+        assert_eq!(*entities.get::<u32>(10).unwrap(), 15);
+        Ok(())
+    }
+
+    #[test]
+    fn components_outside_the_query_or_borrowed_are_rejected() -> Result<()> {
+        let entities = initialize_entities(10)?;
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?;
+
+        assert!(matches!(query.par_for_each_mut::<f32>(4, |_, _| ()), Err(Error::ComponentNotFound(_))));
+
+        let _held = entities.get::<u32>(3).unwrap();
+        assert!(matches!(query.par_for_each_mut::<u32>(4, |_, _| ()), Err(Error::ComponentAlreadyBorrowed(_))));
+        Ok(())
+    }
+}
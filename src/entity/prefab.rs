@@ -0,0 +1,250 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::handle::Entity;
+use super::hierarchy::{Children, Parent};
+use super::transfer::{clone_component, register_component, CloneFn, RegisterFn};
+use super::{Component, Entites, StorageType};
+use crate::entity::error::{Error, Result};
+
+#[derive(Debug, Clone)]
+struct PrefabComponent {
+    type_id: TypeId,
+    value: Component,
+    clone: CloneFn,
+    register: RegisterFn,
+}
+
+/// A template for an entity and its children. Every instance gets its own
+/// copy of the components, so a prefab can be spawned any number of times.
+/// ```
+/// use::ecs_library::{Prefab, World};
+/// #[derive(Clone)]
+/// struct Health(u32);
+/// let goblin = Prefab::new().with(Health(10)).with(1.5_f32);
+///
+/// let mut world = World::new();
+/// let weak = world.spawn_prefab(&goblin).unwrap();
+/// let strong = world.spawn_prefab_with(&goblin, &Prefab::new().with(Health(30))).unwrap();
+/// assert_eq!(world.get::<Health>(weak.index()).unwrap().0, 10);
+/// assert_eq!(world.get::<Health>(strong.index()).unwrap().0, 30);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Prefab {
+    components: Vec<PrefabComponent>,
+    children: Vec<Prefab>,
+}
+
+impl Prefab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a component, replacing one of the same type.
+    pub fn with<T: Any + Clone>(mut self, value: T) -> Self {
+        self.insert(PrefabComponent {
+            type_id: TypeId::of::<T>(),
+            value: Rc::new(RefCell::new(value)),
+            clone: clone_component::<T>,
+            register: register_component::<T>,
+        });
+        self
+    }
+
+    pub fn with_child(mut self, child: Prefab) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn contains<T: Any>(&self) -> bool {
+        self.components.iter().any(|component| component.type_id == TypeId::of::<T>())
+    }
+
+    pub fn children(&self) -> &[Prefab] {
+        &self.children
+    }
+
+    fn insert(&mut self, component: PrefabComponent) {
+        self.components.retain(|other| other.type_id != component.type_id);
+        self.components.push(component);
+    }
+}
+
+impl Entites {
+    /// Captures the cloneable components of the entity and, recursively, of its children.
+    pub fn prefab_of(&self, index: usize) -> Result<Prefab> {
+        let mut prefab = self.components_prefab(index)?;
+        for child in self.children(index) {
+            prefab.children.push(self.prefab_of(child)?);
+        }
+        Ok(prefab)
+    }
+
+    /// Components that are not cloneable are left out, and so are parent and children.
+    fn components_prefab(&self, index: usize) -> Result<Prefab> {
+        if self.entity_handle(index).is_none() {
+            return Err(Error::EntityDoesNotExist(format!("entity {index} does not exist")));
+        }
+        let mut components = self.components_of(index)?;
+        components.sort_by_key(|(type_id, _component)| self.bit_masks[type_id]);
+
+        let mut prefab = Prefab::new();
+        for (type_id, value) in components {
+            if type_id == TypeId::of::<Parent>() || type_id == TypeId::of::<Children>() {
+                continue;
+            }
+            let fns = self.transfer_fns[&type_id];
+            if let Some(clone) = fns.clone {
+                prefab.insert(PrefabComponent { type_id, value, clone, register: fns.register });
+            }
+        }
+        Ok(prefab)
+    }
+
+    /// A new entity with copies of the cloneable components of the one at `index`,
+    /// under the same parent. Children are not cloned; use `prefab_of` for that.
+    pub fn clone_entity(&mut self, index: usize) -> Result<Entity> {
+        let prefab = self.components_prefab(index)?;
+        let clone = self.spawn(&prefab, &[], self.parent(index))?;
+        Ok(self.entity_handle(clone).unwrap())
+    }
+
+    /// Registers the prefab's components where needed, together with their clone
+    /// functions, and creates its entities. Fails with `TooManyComponents`,
+    /// leaving no entity behind, when a component no longer fits.
+    pub fn spawn_prefab(&mut self, prefab: &Prefab) -> Result<Entity> {
+        self.spawn_prefab_with(prefab, &Prefab::new())
+    }
+
+    /// Like `spawn_prefab`, with the components of `overrides` replacing or
+    /// adding to those of the root entity.
+    pub fn spawn_prefab_with(&mut self, prefab: &Prefab, overrides: &Prefab) -> Result<Entity> {
+        let root = self.spawn(prefab, &overrides.components, None)?;
+        Ok(self.entity_handle(root).unwrap())
+    }
+
+    /// Either the whole entity with its children is spawned or, on error, none of it is.
+    fn spawn(&mut self, prefab: &Prefab, overrides: &[PrefabComponent], parent: Option<usize>) -> Result<usize> {
+        let mut components = prefab.components.clone();
+        for component in overrides {
+            components.retain(|other| other.type_id != component.type_id);
+            components.push(component.clone());
+        }
+        if components.is_empty() {
+            return Err(Error::ComponentNotFound("a prefab entity needs at least one component".to_string()));
+        }
+
+        self.create_entity();
+        let index = self.inserting_into_index;
+        let spawned = self.fill(index, prefab, &components, parent);
+        if spawned.is_err() && self.entity_handle(index).is_some() {
+            self.delete_entity_by_id(index)?;
+        }
+        spawned.map(|_| index)
+    }
+
+    fn fill(&mut self, index: usize, prefab: &Prefab, components: &[PrefabComponent], parent: Option<usize>) -> Result<()> {
+        for component in components {
+            (component.register)(self, StorageType::Table)?;
+            self.transfer_fns.get_mut(&component.type_id).unwrap().clone.get_or_insert(component.clone);
+            let value = (component.clone)(&*component.value.borrow());
+            self.insert_component(component.type_id, index, value)?;
+        }
+        if let Some(parent) = parent {
+            self.add_child(parent, index)?;
+        }
+        for child in &prefab.children {
+            self.spawn(child, &[], Some(index))?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn prefabs_spawn_with_children_and_overrides() -> Result<()> {
+        let mut entities = Entites::default();
+        let turret = Prefab::new()
+            .with(100_u32)
+            .with_child(Prefab::new().with(1.0_f32))
+            .with_child(Prefab::new().with(2.0_f32));
+
+        let first = entities.spawn_prefab(&turret)?;
+        let second = entities.spawn_prefab_with(&turret, &Prefab::new().with(50_u32).with(true))?;
+
+        assert_eq!(entities.children(first.index()), vec![1, 2]);
+        assert_eq!(*entities.get::<f32>(2).unwrap(), 2.0);
+        assert_eq!(*entities.get::<u32>(first.index()).unwrap(), 100);
+        assert_eq!(*entities.get::<u32>(second.index()).unwrap(), 50);
+        assert!(entities.has::<bool>(second.index()));
+        assert_eq!(entities.children(second.index()).len(), 2);
+
+        *entities.get_mut::<u32>(first.index()).unwrap() = 1;
+        let third = entities.spawn_prefab(&turret)?;
+        assert_eq!(*entities.get::<u32>(third.index()).unwrap(), 100);
+
+        let copy = entities.clone_entity(third.index())?;
+        assert_eq!(*entities.get::<u32>(copy.index()).unwrap(), 100);
+        Ok(())
+    }
+
+    #[test]
+    fn failed_spawns_leave_nothing_behind() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_unique_component::<u8>();
+        entities.register_clone::<u8>();
+        entities.create_entity().with_component(1_u8)?;
+        let boss = Prefab::new()
+            .with(10_u32)
+            .with_child(Prefab::new().with(1.0_f32))
+            .with_child(Prefab::new().with(2_u8));
+
+        assert!(matches!(entities.spawn_prefab(&boss), Err(Error::UniqueComponentTaken(_))));
+        assert_eq!(entities.iter_entities().count(), 1);
+
+        assert!(matches!(entities.clone_entity(0), Err(Error::UniqueComponentTaken(_))));
+        assert_eq!(entities.iter_entities().count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn spawning_into_a_full_world_reports_too_many_components() -> Result<()> {
+        let mut entities = Entites::default();
+        for bit in 0..u32::BITS {
+            entities.register_dynamic_component(&bit.to_string())?;
+        }
+
+        let result = entities.spawn_prefab(&Prefab::new().with(1_u32));
+
+        assert!(matches!(result, Err(Error::TooManyComponents(_))));
+        assert_eq!(entities.iter_entities().count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn clone_entity_skips_uncloneable_components() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_clone::<u32>();
+        entities.register_component::<f32>();
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?.with_component(0.5_f32)?;
+        entities.add_child(0, 1)?;
+
+        let clone = entities.clone_entity(1)?;
+
+        assert_eq!(*entities.get::<u32>(clone.index()).unwrap(), 2);
+        assert!(!entities.has::<f32>(clone.index()));
+        assert_eq!(entities.children(0), vec![1, clone.index()]);
+
+        let prefab = entities.prefab_of(0)?;
+        assert!(prefab.contains::<u32>());
+        assert_eq!(prefab.children().len(), 2);
+        Ok(())
+    }
+}
@@ -2,16 +2,71 @@ use std::any::{Any, TypeId};
 
 
 use super::{Entites, Component};
+use super::dynamic::ComponentId;
+use super::query_state::QueryState;
+use super::handle::Entity;
 use crate::entity::error::{Result, Error};
 
 pub type QueryIndexes = Vec<usize>;
 pub type QueryComponents = Vec<Vec<Component>>;
+/// One row per matching entity: its handle and the query's components in query order.
+pub type QueryItems = Vec<(Entity, Vec<Component>)>;
+
+/// Turns the column layout of `run` into rows carrying entity handles.
+/// Rows of slots without a live entity are dropped.
+pub(crate) fn into_items(entities: &Entites, indexes: QueryIndexes, components: QueryComponents) -> QueryItems {
+    let mut columns = components.into_iter().map(Vec::into_iter).collect::<Vec<_>>();
+    indexes
+        .into_iter()
+        .filter_map(|index| {
+            let row = columns.iter_mut().map(|column| column.next().unwrap()).collect();
+            Some((entities.entity_handle(index)?, row))
+        })
+        .collect()
+}
+
+/// One column per component, static components first, for the given entities.
+pub(crate) fn collect_components(
+    entities: &Entites,
+    type_ids: &[TypeId],
+    dynamic_ids: &[ComponentId],
+    indexes: &[usize],
+) -> QueryComponents {
+    let mut result = vec![];
+
+    for type_id in type_ids {
+        let query_components = if let Some(components) = entities.components.get(type_id) {
+            indexes
+                .iter()
+                .map(|index| components[*index].as_ref().unwrap().clone())
+                .collect()
+        } else {
+            indexes
+                .iter()
+                .map(|index| entities.component_at(type_id, *index).unwrap().clone())
+                .collect()
+        };
+        result.push(query_components)
+    }
+
+    for id in dynamic_ids {
+        let components = entities.dynamic_components.get(id).unwrap();
+        let query_components = indexes
+            .iter()
+            .map(|index| components[*index].as_ref().unwrap().clone())
+            .collect();
+        result.push(query_components)
+    }
+
+    result
+}
 
 #[derive(Debug)]
 pub struct Query<'a> {
     map: u32,
     entities: &'a Entites,
     type_ids: Vec<TypeId>,
+    dynamic_ids: Vec<ComponentId>,
 }
 
 impl<'a> Query<'a> {
@@ -21,44 +76,139 @@ impl<'a> Query<'a> {
             entities, 
             map:0,
             type_ids: vec![],
+            dynamic_ids: vec![],
         }
     }
 
+    /// Fails with `ComponetNotRegister` for unregistered components. Leaving them
+    /// out instead would match entities without them and return fewer columns
+    /// than were asked for.
     pub fn with_component<T: Any>(&mut self) -> Result<&mut Self> {
         let type_id = TypeId::of::<T>();
         if let Some(bit_mask) = self.entities.get_bitmask(&type_id){
             self.map |= bit_mask;
             self.type_ids.push(type_id);
         } else{
-             Error::ComponetNotRegister("attempting use component that wasn't registerd".to_string());
+            return Err(
+                Error::ComponetNotRegister("attempting use component that wasn't registerd".to_string())
+            );
         }
         Ok(self)
     }
 
+    /// Dynamic component columns come after the static ones in the query result,
+    /// each value being the `Vec<u8>` the component was inserted with.
+    pub fn with_dynamic_component(&mut self, id: ComponentId) -> Result<&mut Self> {
+        if let Some(bit_mask) = self.entities.get_dynamic_bitmask(&id) {
+            self.map |= bit_mask;
+            self.dynamic_ids.push(id);
+        } else {
+            return Err(
+                Error::ComponetNotRegister("attempting use dynamic component that wasn't registerd".to_string())
+            );
+        }
+        Ok(self)
+    }
+
+    /// Matching entity indexes in ascending order. Slots without any component
+    /// are never matched, not even by a query without components.
+    /// When the query contains sparse-set components only the entities
+    /// of the smallest set are checked instead of every entity.
+    fn matching_indexes(&self) -> QueryIndexes {
+        let smallest_sparse_set = self.type_ids
+            .iter()
+            .filter_map(|type_id| self.entities.sparse_sets.get(type_id))
+            .min_by_key(|sparse_set| sparse_set.len());
+
+        if let Some(sparse_set) = smallest_sparse_set {
+            let mut indexes = sparse_set.entities()
+                .iter()
+                .copied()
+                .filter(|idx| self.matches(*idx))
+                .collect::<Vec<_>>();
+            indexes.sort_unstable();
+            return indexes;
+        }
+
+        (0..self.entities.map.len())
+            .filter(|idx| self.matches(*idx))
+            .collect::<Vec<_>>()
+    }
+
     pub fn run(&self) -> (QueryIndexes, QueryComponents) {
-        let indexes = self.entities.map.iter().enumerate()
-            .filter_map(|(idx, entity_map)| {
-                if entity_map & self.map == self.map {
-                    Some(idx)
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
+        let indexes = self.matching_indexes();
         //dbg!(indexes);
 
-        let mut result = vec![];
+        let result = collect_components(self.entities, &self.type_ids, &self.dynamic_ids, &indexes);
+
+        (indexes,result)
+    }
+
+    /// Like `run` but each item carries the `Entity` handle next to its components.
+    pub fn run_entities(&self) -> QueryItems {
+        let (indexes, components) = self.run();
+        into_items(self.entities, indexes, components)
+    }
+
+    /// A reusable version of this query that no longer borrows the world.
+    pub fn state(&self) -> QueryState {
+        QueryState::new(self.map, self.type_ids.clone(), self.dynamic_ids.clone())
+    }
+
+    pub(crate) fn entities(&self) -> &'a Entites {
+        self.entities
+    }
+
+    pub fn type_ids(&self) -> &[TypeId] {
+        &self.type_ids
+    }
+
+    /// Position of `T` among the query's columns.
+    pub(crate) fn column_of<T: Any>(&self) -> Result<usize> {
+        self.type_ids
+            .iter()
+            .position(|type_id| *type_id == TypeId::of::<T>())
+            .ok_or_else(|| Error::ComponentNotFound(
+                format!("{} is not part of the query", std::any::type_name::<T>())
+            ))
+    }
+
+    fn matches(&self, index: usize) -> bool {
+        self.entities.map.get(index).is_some_and(|entity_map| *entity_map != 0 && entity_map & self.map == self.map)
+    }
 
-        for type_id in &self.type_ids {
-            let components = self.entities.components.get(type_id).unwrap();
-            let mut query_components = Vec::new();
-            for index in &indexes {
-                query_components.push(components[*index].as_ref().unwrap().clone());
-            }
-            result.push(query_components)
+    /// The query's components for one entity, in the same order as the columns of `run`.
+    pub fn get(&self, index: usize) -> Option<Vec<Component>> {
+        if !self.matches(index) {
+            return None;
         }
+        let static_components = self.type_ids
+            .iter()
+            .map(|type_id| self.entities.component_at(type_id, index).cloned());
+        let dynamic_components = self.dynamic_ids
+            .iter()
+            .map(|id| self.entities.dynamic_components.get(id)?.get(index)?.clone());
+        static_components.chain(dynamic_components).collect()
+    }
 
-        (indexes,result)
+    /// The only matching entity, or an error when there are none or several.
+    pub fn single(&self) -> Result<(usize, Vec<Component>)> {
+        let indexes = self.matching_indexes();
+        match indexes.as_slice() {
+            [index] => Ok((*index, self.get(*index).unwrap())),
+            _ => Err(Error::NotExactlyOneMatch(format!("query matched {} entities", indexes.len()))),
+        }
+    }
+
+    /// Number of matching entities. Only the entity masks are looked at.
+    pub fn count(&self) -> usize {
+        (0..self.entities.map.len())
+            .filter(|idx| self.matches(*idx))
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !(0..self.entities.map.len()).any(|idx| self.matches(idx))
     }
 
 }
@@ -67,6 +217,7 @@ impl<'a> Query<'a> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::entity::StorageType;
     use anyhow::Result;
 
     #[test]
@@ -85,6 +236,18 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn unregistered_components_are_rejected() {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+
+        let mut query = Query::new(&entities);
+
+        assert!(matches!(query.with_component::<f32>(), Err(Error::ComponetNotRegister(_))));
+        assert_eq!(query.map, 0);
+        assert!(query.type_ids.is_empty());
+    }
+
 
     #[test]
     fn run_qurey() -> Result<()> {
@@ -131,5 +294,136 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn run_entities_pairs_handles_with_components() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+
+        entities.create_entity().with_component(10_u32)?.with_component(1.0_f32)?;
+        entities.create_entity().with_component(5_u32)?;
+        entities.create_entity().with_component(15_u32)?.with_component(2.0_f32)?;
+
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?.with_component::<f32>()?;
+        let items = query.run_entities();
+
+        assert_eq!(items.len(), 2);
+        let (entity, components) = &items[1];
+        assert_eq!(entity.index(), 2);
+        assert!(entities.is_alive(*entity));
+        assert_eq!(*components[0].borrow().downcast_ref::<u32>().unwrap(), 15);
+        assert_eq!(*components[1].borrow().downcast_ref::<f32>().unwrap(), 2.0);
+        Ok(())
+    }
+
+    #[test]
+    fn empty_query_skips_deleted_entities() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?;
+        entities.delete_entity_by_id(0)?;
+
+        let items = Query::new(&entities).run_entities();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].0.index(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn count_skips_deleted_entities() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+        entities.delete_entity_by_id(0)?;
+
+        let query = Query::new(&entities);
+
+        assert_eq!(query.count(), 0);
+        assert!(query.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn get_single_and_count() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+
+        entities.create_entity().with_component(10_u32)?.with_component(20.2_f32)?;
+        entities.create_entity().with_component(5_u32)?;
+        entities.create_entity().with_component(15_u32)?;
+
+        let mut query = Query::new(&entities);
+        query.with_component::<f32>()?
+            .with_component::<u32>()?;
+
+        assert_eq!(query.count(), 1);
+        assert!(!query.is_empty());
+        assert!(query.get(1).is_none());
+        assert!(query.get(7).is_none());
+        let components = query.get(0).unwrap();
+        assert_eq!(*components[1].borrow().downcast_ref::<u32>().unwrap(), 10);
+
+        let (index, components) = query.single()?;
+        assert_eq!(index, 0);
+        assert_eq!(*components[0].borrow().downcast_ref::<f32>().unwrap(), 20.2);
+
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?;
+        assert_eq!(query.count(), 3);
+        assert!(matches!(query.single(), Err(Error::NotExactlyOneMatch(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn run_query_mixing_sparse_set_and_table_components() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component_with_storage::<f32>(StorageType::SparseSet);
+
+        for value in 0..4_u32 {
+            entities.create_entity().with_component(value)?;
+        }
+        entities.add_component_to_entity_by_id(3.5_f32, 3)?;
+        entities.add_component_to_entity_by_id(1.5_f32, 1)?;
+
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?
+            .with_component::<f32>()?;
+        let (indexes, components) = query.run();
+
+        assert_eq!(indexes, vec![1, 3]);
+        let borrowed_u32 = components[0][1].borrow();
+        assert_eq!(*borrowed_u32.downcast_ref::<u32>().unwrap(), 3);
+        let borrowed_f32 = components[1][0].borrow();
+        assert_eq!(*borrowed_f32.downcast_ref::<f32>().unwrap(), 1.5);
+        Ok(())
+    }
+
+    #[test]
+    fn run_query_with_dynamic_component() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        let armor = entities.register_dynamic_component("armor")?;
+
+        entities.create_entity().with_component(10_u32)?;
+        entities.create_entity().with_component(15_u32)?.with_dynamic_component(armor, vec![7])?;
+
+        let mut query = Query::new(&entities);
+        query.with_dynamic_component(armor)?
+            .with_component::<u32>()?;
+        let (indexes, components) = query.run();
+
+        assert_eq!(indexes, vec![1]);
+        let borrowed_u32 = components[0][0].borrow();
+        assert_eq!(*borrowed_u32.downcast_ref::<u32>().unwrap(), 15);
+        let borrowed_armor = components[1][0].borrow();
+        assert_eq!(*borrowed_armor.downcast_ref::<Vec<u8>>().unwrap(), vec![7]);
+        Ok(())
+    }
+
 }
 
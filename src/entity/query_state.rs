@@ -0,0 +1,156 @@
+use std::any::TypeId;
+
+use super::dynamic::ComponentId;
+use super::query::{collect_components, into_items, QueryComponents, QueryIndexes, QueryItems};
+use super::Entites;
+
+/// A query that remembers which archetypes match it.
+/// Each run only checks archetypes created since the previous run and then
+/// visits the entities of the matching archetypes, instead of every entity.
+/// A state must only be run against the `Entites` it was made from,
+/// since archetype positions mean nothing in another one.
+#[derive(Debug, Clone)]
+pub struct QueryState {
+    map: u32,
+    type_ids: Vec<TypeId>,
+    dynamic_ids: Vec<ComponentId>,
+    matched_archetypes: Vec<usize>,
+    archetypes_seen: usize,
+}
+
+impl QueryState {
+    pub(crate) fn new(map: u32, type_ids: Vec<TypeId>, dynamic_ids: Vec<ComponentId>) -> Self {
+        Self {
+            map,
+            type_ids,
+            dynamic_ids,
+            matched_archetypes: vec![],
+            archetypes_seen: 0,
+        }
+    }
+
+    fn update_archetypes(&mut self, entities: &Entites) {
+        let archetypes = entities.archetypes();
+        for (position, archetype) in archetypes.iter().enumerate().skip(self.archetypes_seen) {
+            if archetype.mask() & self.map == self.map {
+                self.matched_archetypes.push(position);
+            }
+        }
+        self.archetypes_seen = archetypes.len();
+    }
+
+    /// Matching entity indexes in ascending order.
+    pub fn indexes(&mut self, entities: &Entites) -> QueryIndexes {
+        self.update_archetypes(entities);
+        let archetypes = entities.archetypes();
+        let mut indexes = self.matched_archetypes
+            .iter()
+            .flat_map(|position| archetypes[*position].entities().copied())
+            .collect::<Vec<_>>();
+        indexes.sort_unstable();
+        indexes
+    }
+
+    /// Same indexes and components, in the same order, as `Query::run` would return now.
+    pub fn run(&mut self, entities: &Entites) -> (QueryIndexes, QueryComponents) {
+        let indexes = self.indexes(entities);
+        let result = collect_components(entities, &self.type_ids, &self.dynamic_ids, &indexes);
+        (indexes, result)
+    }
+
+    /// Same rows as `Query::run_entities` would return now.
+    pub fn run_entities(&mut self, entities: &Entites) -> QueryItems {
+        let (indexes, components) = self.run(entities);
+        into_items(entities, indexes, components)
+    }
+
+    /// Same as `Query::count`.
+    pub fn count(&mut self, entities: &Entites) -> usize {
+        self.update_archetypes(entities);
+        let archetypes = entities.archetypes();
+        self.matched_archetypes
+            .iter()
+            .map(|position| archetypes[*position].len())
+            .sum()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::query::Query;
+    use anyhow::Result;
+
+    #[test]
+    fn state_picks_up_new_archetypes() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        entities.register_component::<bool>();
+        entities.create_entity().with_component(1_u32)?.with_component(1.0_f32)?;
+        entities.create_entity().with_component(2_u32)?;
+
+        let mut state = Query::new(&entities).with_component::<u32>()?.with_component::<f32>()?.state();
+        assert_eq!(state.indexes(&entities), vec![0]);
+        assert_eq!(state.matched_archetypes, vec![1]);
+
+        entities.create_entity().with_component(3_u32)?.with_component(3.0_f32)?.with_component(true)?;
+        entities.add_component_to_entity_by_id(2.0_f32, 1)?;
+        let (indexes, components) = state.run(&entities);
+
+        assert_eq!(indexes, vec![0, 1, 2]);
+        assert_eq!(*components[0][2].borrow().downcast_ref::<u32>().unwrap(), 3);
+        assert_eq!(state.count(&entities), 3);
+
+        entities.delete_entity_by_id(0)?;
+        assert_eq!(state.indexes(&entities), vec![1, 2]);
+        Ok(())
+    }
+    #[test]
+    fn state_matches_query_run() -> Result<()> {
+        struct Selected;
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component_with_storage::<f32>(crate::StorageType::SparseSet);
+        entities.register_component::<Selected>();
+        let armor = entities.register_dynamic_component("armor")?;
+        entities.register_codec::<u32>();
+
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?.with_component::<f32>()?.with_component::<Selected>()?.with_dynamic_component(armor)?;
+        let mut state = query.state();
+        let mut empty_state = Query::new(&entities).state();
+
+        let check = |entities: &Entites, state: &mut QueryState, empty_state: &mut QueryState| -> Result<()> {
+            let mut query = Query::new(entities);
+            query.with_component::<u32>()?.with_component::<f32>()?.with_component::<Selected>()?.with_dynamic_component(armor)?;
+            let (indexes, components) = query.run();
+            let (state_indexes, state_components) = state.run(entities);
+            assert_eq!(state_indexes, indexes);
+            for (column, state_column) in components.iter().zip(&state_components) {
+                assert!(column.iter().zip(state_column).all(|(a, b)| std::rc::Rc::ptr_eq(a, b)));
+            }
+            assert_eq!(state.count(entities), query.count());
+            assert_eq!(empty_state.indexes(entities), Query::new(entities).run().0);
+            Ok(())
+        };
+
+        for value in 0..6_u32 {
+            let entity = entities.create_entity().with_component(value)?;
+            if value % 2 == 0 {
+                entity.with_component(value as f32)?.with_component(Selected)?.with_dynamic_component(armor, vec![1])?;
+            }
+        }
+        check(&entities, &mut state, &mut empty_state)?;
+
+        let bytes = entities.save_snapshot();
+        entities.delete_entity_by_id(0)?;
+        entities.delete_component_by_entity_id::<Selected>(2)?;
+        check(&entities, &mut state, &mut empty_state)?;
+
+        entities.load_snapshot(&bytes)?;
+        check(&entities, &mut state, &mut empty_state)?;
+        Ok(())
+    }
+}
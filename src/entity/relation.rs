@@ -0,0 +1,295 @@
+use std::any::{Any, TypeId};
+use std::collections::{BTreeSet, HashMap};
+use std::marker::PhantomData;
+
+use super::transfer::{EntityMap, MapEntities};
+use super::{Entites, StorageType};
+use crate::entity::error::Result;
+
+/// Component holding every target of relation kind `R` for one entity,
+/// e.g. `Relation<Likes>` on an entity lists everything it likes.
+#[derive(Debug)]
+pub struct Relation<R> {
+    targets: Vec<usize>,
+    kind: PhantomData<R>,
+}
+
+impl<R> Relation<R> {
+    pub fn targets(&self) -> &[usize] {
+        &self.targets
+    }
+}
+
+impl<R> Clone for Relation<R> {
+    fn clone(&self) -> Self {
+        Self { targets: self.targets.clone(), kind: PhantomData }
+    }
+}
+
+/// Targets that were not transferred along are dropped, and so is
+/// the relation once it has none left.
+impl<R> MapEntities for Relation<R> {
+    fn map_entities(&mut self, map: &EntityMap) -> bool {
+        self.targets.retain(|target| map.get(*target).is_some());
+        for target in &mut self.targets {
+            *target = map.get(*target).unwrap();
+        }
+        !self.targets.is_empty()
+    }
+}
+
+/// Which entities point at each target, so finding them does not scan the world.
+/// Entries may outlive the relation they were made for and are checked on use.
+/// Sources handed out through `get_mut` are kept apart until their value can be read again.
+pub(crate) struct RelationSources {
+    by_target: HashMap<usize, BTreeSet<usize>>,
+    dirty: BTreeSet<usize>,
+    targets: fn(&dyn Any) -> Vec<usize>,
+}
+
+impl RelationSources {
+    fn of<R: Any>() -> Self {
+        Self { by_target: HashMap::new(), dirty: BTreeSet::new(), targets: relation_targets_of::<R> }
+    }
+
+    fn record(&mut self, source: usize, targets: Vec<usize>) {
+        for target in targets {
+            self.by_target.entry(target).or_default().insert(source);
+        }
+    }
+}
+
+fn relation_targets_of<R: Any>(data: &dyn Any) -> Vec<usize> {
+    data.downcast_ref::<Relation<R>>().map(|relation| relation.targets.clone()).unwrap_or_default()
+}
+
+fn register_relation_in<R: Any>(entities: &mut Entites, _storage: StorageType) -> Result<()> {
+    entities.register_relation::<R>()
+}
+
+fn remove_relations_to<R: Any>(entities: &mut Entites, target: usize) {
+    entities.settle_relation_sources::<R>();
+    for source in entities.entities_with_relation::<R>(target) {
+        let _ = entities.remove_relation::<R>(source, target);
+    }
+    if let Some(sources) = entities.relation_sources.get_mut(&TypeId::of::<Relation<R>>()) {
+        sources.by_target.remove(&target);
+    }
+}
+
+impl Entites {
+    fn register_relation<R: Any>(&mut self) -> Result<()> {
+        self.try_register_component_with_storage::<Relation<R>>(StorageType::Table)?;
+        self.relation_sources.entry(TypeId::of::<Relation<R>>()).or_insert_with(RelationSources::of::<R>);
+        self.register_clone::<Relation<R>>();
+        self.register_map_entities::<Relation<R>>();
+        self.set_register_fn(&TypeId::of::<Relation<R>>(), register_relation_in::<R>);
+        self.relation_cleanups.insert(TypeId::of::<R>(), remove_relations_to::<R>);
+        Ok(())
+    }
+
+    /// Adding the same pair twice keeps a single relation.
+    /// Both entities have to be alive.
+    pub fn add_relation<R: Any>(&mut self, entity: usize, target: usize) -> Result<()> {
+        self.ensure_exists(entity)?;
+        self.ensure_exists(target)?;
+        self.register_relation::<R>()?;
+
+        let added = self.component_mut::<Relation<R>>(entity)
+            .map(|mut relation| {
+                if !relation.targets.contains(&target) {
+                    relation.targets.push(target);
+                }
+            })
+            .is_some();
+        if added {
+            self.relation_sources.get_mut(&TypeId::of::<Relation<R>>()).unwrap().record(entity, vec![target]);
+        } else {
+            let relation = Relation::<R> { targets: vec![target], kind: PhantomData };
+            self.add_component_to_entity_by_id(relation, entity)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_relation<R: Any>(&mut self, entity: usize, target: usize) -> Result<()> {
+        let no_targets_left = self.component_mut::<Relation<R>>(entity)
+            .map(|mut relation| {
+                relation.targets.retain(|index| *index != target);
+                relation.targets.is_empty()
+            })
+            .unwrap_or(false);
+        if let Some(sources) = self.relation_sources.get_mut(&TypeId::of::<Relation<R>>()) {
+            if let Some(sources) = sources.by_target.get_mut(&target) {
+                sources.remove(&entity);
+            }
+        }
+        if no_targets_left {
+            self.delete_component_by_entity_id::<Relation<R>>(entity)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn has_relation<R: Any>(&self, entity: usize, target: usize) -> bool {
+        self.component_ref::<Relation<R>>(entity)
+            .is_some_and(|relation| relation.targets.contains(&target))
+    }
+
+    /// All targets of relation `R` on `entity`.
+    pub fn relation_targets<R: Any>(&self, entity: usize) -> Vec<usize> {
+        self.component_ref::<Relation<R>>(entity)
+            .map(|relation| relation.targets.clone())
+            .unwrap_or_default()
+    }
+
+    /// All entities that have relation `R` to `target`, in index order.
+    pub fn entities_with_relation<R: Any>(&self, target: usize) -> Vec<usize> {
+        let Some(sources) = self.relation_sources.get(&TypeId::of::<Relation<R>>()) else {
+            return vec![];
+        };
+        let recorded = sources.by_target.get(&target).into_iter().flatten();
+        recorded
+            .chain(&sources.dirty)
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter(|entity| self.has_relation::<R>(*entity, target))
+            .collect()
+    }
+
+    /// Records the targets of a relation component that was just stored.
+    pub(crate) fn relation_inserted(&mut self, type_id: &TypeId, index: usize) {
+        let Some(targets) = self.relation_sources.get(type_id).map(|sources| sources.targets) else {
+            return;
+        };
+        let targets = self.component_at(type_id, index).map(|component| targets(&*component.borrow()));
+        if let Some(targets) = targets {
+            self.relation_sources.get_mut(type_id).unwrap().record(index, targets);
+        }
+    }
+
+    pub(crate) fn relation_mutated(&mut self, type_id: &TypeId, index: usize) {
+        if let Some(sources) = self.relation_sources.get_mut(type_id) {
+            sources.dirty.insert(index);
+        }
+    }
+
+    /// Records the current targets of the sources handed out through `get_mut`.
+    fn settle_relation_sources<R: Any>(&mut self) {
+        let type_id = TypeId::of::<Relation<R>>();
+        let Some(sources) = self.relation_sources.get_mut(&type_id) else {
+            return;
+        };
+        for source in std::mem::take(&mut sources.dirty) {
+            let targets = self.relation_targets::<R>(source);
+            self.relation_sources.get_mut(&type_id).unwrap().record(source, targets);
+        }
+    }
+
+    /// Called for every deleted entity so no relation keeps pointing at it.
+    pub(crate) fn remove_relations_targeting(&mut self, target: usize) {
+        let cleanups = self.relation_cleanups.values().copied().collect::<Vec<_>>();
+        for cleanup in cleanups {
+            cleanup(self, target);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::error::Error;
+    use anyhow::Result;
+
+    struct Likes;
+    struct Eats;
+
+    #[test]
+    fn add_and_query_relations() -> Result<()> {
+        let mut entities = initialize_entities(3)?;
+        entities.add_relation::<Likes>(0, 1)?;
+        entities.add_relation::<Likes>(0, 2)?;
+        entities.add_relation::<Likes>(0, 2)?;
+        entities.add_relation::<Likes>(2, 1)?;
+        entities.add_relation::<Eats>(1, 2)?;
+
+        assert_eq!(entities.relation_targets::<Likes>(0), vec![1, 2]);
+        assert_eq!(entities.entities_with_relation::<Likes>(1), vec![0, 2]);
+        assert_eq!(entities.entities_with_relation::<Eats>(2), vec![1]);
+        assert!(!entities.has_relation::<Eats>(0, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn removing_last_target_removes_component() -> Result<()> {
+        let mut entities = initialize_entities(2)?;
+        entities.add_relation::<Likes>(0, 1)?;
+        entities.remove_relation::<Likes>(0, 1)?;
+
+        assert!(entities.relation_targets::<Likes>(0).is_empty());
+        assert_eq!(entities.map[0], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn deleting_target_removes_relations() -> Result<()> {
+        let mut entities = initialize_entities(3)?;
+        entities.add_relation::<Likes>(0, 1)?;
+        entities.add_relation::<Likes>(0, 2)?;
+        entities.add_relation::<Eats>(2, 1)?;
+
+        entities.delete_entity_by_id(1)?;
+
+        assert_eq!(entities.relation_targets::<Likes>(0), vec![2]);
+        assert!(entities.relation_targets::<Eats>(2).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn sources_are_found_after_any_change() -> Result<()> {
+        let mut entities = initialize_entities(4)?;
+        entities.register_clone::<u32>();
+        entities.add_relation::<Likes>(0, 1)?;
+        entities.add_relation::<Likes>(2, 3)?;
+        let snapshot = entities.snapshot()?;
+
+        let replacement = entities.get::<Relation<Likes>>(2).unwrap().clone();
+        *entities.get_mut::<Relation<Likes>>(0).unwrap() = replacement;
+        assert_eq!(entities.entities_with_relation::<Likes>(3), vec![0, 2]);
+        assert!(entities.entities_with_relation::<Likes>(1).is_empty());
+
+        entities.delete_entity_by_id(3)?;
+        assert!(entities.relation_targets::<Likes>(0).is_empty());
+        assert!(entities.relation_targets::<Likes>(2).is_empty());
+
+        entities.restore(&snapshot)?;
+        assert_eq!(entities.entities_with_relation::<Likes>(1), vec![0]);
+        entities.delete_entity_by_id(1)?;
+        assert!(entities.relation_targets::<Likes>(0).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn only_live_entities_can_be_related() -> Result<()> {
+        let mut entities = initialize_entities(3)?;
+        entities.delete_entity_by_id(1)?;
+
+        assert!(matches!(entities.add_relation::<Likes>(0, 1), Err(Error::EntityDoesNotExist(_))));
+        assert!(matches!(entities.add_relation::<Likes>(1, 0), Err(Error::EntityDoesNotExist(_))));
+        assert!(matches!(entities.add_relation::<Likes>(0, 7), Err(Error::EntityDoesNotExist(_))));
+        assert!(entities.relation_targets::<Likes>(0).is_empty());
+        assert!(entities.entity_handle(1).is_none());
+        Ok(())
+    }
+
+    fn initialize_entities(count: usize) -> Result<Entites> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        for index in 0..count {
+            entities.create_entity().with_component(index as u32)?;
+        }
+        Ok(entities)
+    }
+}
@@ -0,0 +1,205 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::dynamic::ComponentId;
+use super::{Component, Entites};
+use crate::entity::error::{Error, Result};
+
+/// In-memory copy of the entities, taken with `Entites::snapshot`.
+/// It holds its own copies of the components, so it can be restored any number of times.
+#[derive(Clone)]
+pub struct EntitesSnapshot {
+    map: Vec<u32>,
+    generations: Vec<u32>,
+    inserting_into_index: usize,
+    components: Vec<(TypeId, usize, Component)>,
+    dynamic: Vec<(ComponentId, usize, Vec<u8>)>,
+}
+
+impl fmt::Debug for EntitesSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntitesSnapshot")
+            .field("slots", &self.map.len())
+            .field("components", &self.components.len())
+            .finish()
+    }
+}
+
+/// Copy of the entities and of the resources registered for snapshots.
+#[derive(Debug, Clone)]
+pub struct WorldSnapshot {
+    pub(crate) entities: EntitesSnapshot,
+    pub(crate) resources: Vec<(TypeId, Rc<dyn Any>)>,
+}
+
+impl Entites {
+    /// Copies every component with its clone function. Tags need none.
+    pub fn snapshot(&self) -> Result<EntitesSnapshot> {
+        let mut components = vec![];
+        let mut dynamic = vec![];
+        for index in self.iter_entities().map(|entity| entity.index()) {
+            let mut entity_components = self.components_of(index)?;
+            entity_components.sort_by_key(|(type_id, _component)| self.bit_masks[type_id]);
+            for (type_id, component) in entity_components {
+                components.push((type_id, index, self.clone_value(&type_id, &component)?));
+            }
+            for id in self.dynamic_components_of(index) {
+                let bytes = self.get_dynamic_component(id, index).unwrap().clone();
+                dynamic.push((id, index, bytes));
+            }
+        }
+        Ok(EntitesSnapshot {
+            map: self.map.clone(),
+            generations: self.generations.clone(),
+            inserting_into_index: self.inserting_into_index,
+            components,
+            dynamic,
+        })
+    }
+
+    /// Puts every entity, slot and generation back the way they were when
+    /// the snapshot was taken. Indexes are rebuilt and every component counts as changed.
+    /// Fails without changing anything when the snapshot does not fit these entities.
+    pub fn restore(&mut self, snapshot: &EntitesSnapshot) -> Result<()> {
+        let components = self.check_restore(snapshot)?;
+        let entity_count = snapshot.map.len();
+        self.clear_indexes();
+        for (index, mask) in self.map.iter().enumerate() {
+            self.record_removed(index, *mask);
+        }
+        for components in self.components.values_mut() {
+            *components = vec![None; entity_count];
+        }
+        for components in self.dynamic_components.values_mut() {
+            *components = vec![None; entity_count];
+        }
+        for sparse_set in self.sparse_sets.values_mut() {
+            sparse_set.clear();
+        }
+        self.map = vec![0; entity_count];
+        self.generations = snapshot.generations.clone();
+        self.clear_archetypes();
+
+        for (type_id, index, component) in components {
+            self.insert_component(type_id, index, component)?;
+        }
+        for (id, index, bytes) in &snapshot.dynamic {
+            self.add_dynamic_component_to_entity_by_id(*id, bytes.clone(), *index)?;
+        }
+        self.inserting_into_index = snapshot.inserting_into_index;
+        Ok(())
+    }
+
+    /// Copies the components of the snapshot and makes sure they rebuild its entity map.
+    fn check_restore(&self, snapshot: &EntitesSnapshot) -> Result<Vec<(TypeId, usize, Component)>> {
+        let mut map = vec![0; snapshot.map.len()];
+        let mut holders = HashMap::new();
+        let mut components = vec![];
+        for (type_id, index, component) in &snapshot.components {
+            let bit_mask = self.get_bitmask(type_id).ok_or_else(
+                || Error::ComponetNotRegister("snapshot holds a component that wasn't registerd".to_string())
+            )?;
+            let slot = map.get_mut(*index).ok_or_else(
+                || Error::InvalidSnapshot("snapshot component belongs to a missing slot".to_string())
+            )?;
+            *slot |= bit_mask;
+            *holders.entry(*type_id).or_insert(0) += 1;
+            components.push((*type_id, *index, self.clone_value(type_id, component)?));
+        }
+        for (id, index, _bytes) in &snapshot.dynamic {
+            let bit_mask = self.get_dynamic_bitmask(id).ok_or_else(
+                || Error::ComponetNotRegister("snapshot holds a dynamic component that wasn't registerd".to_string())
+            )?;
+            let slot = map.get_mut(*index).ok_or_else(
+                || Error::InvalidSnapshot("snapshot component belongs to a missing slot".to_string())
+            )?;
+            *slot |= bit_mask;
+        }
+        if map != snapshot.map || snapshot.generations.len() != map.len() {
+            return Err(Error::InvalidSnapshot("restored entities would not match the snapshot".to_string()));
+        }
+        if let Some((_type_id, count)) = holders.iter().find(|(type_id, count)| self.is_unique(type_id) && **count > 1) {
+            return Err(Error::UniqueComponentTaken(format!("{count} entities in the snapshot share a unique component")));
+        }
+        Ok(components)
+    }
+
+    /// A copy of the component made with its clone function. Tags share their value.
+    pub(crate) fn clone_value(&self, type_id: &TypeId, component: &Component) -> Result<Component> {
+        if self.is_tag(type_id) {
+            return Ok(component.clone());
+        }
+        let clone = self.transfer_fns.get(type_id).and_then(|fns| fns.clone).ok_or_else(|| {
+            Error::ComponentNotCloneable("every component needs a clone function to be snapshotted".to_string())
+        })?;
+        Ok(clone(&*component.borrow()))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    struct Frozen;
+
+    #[test]
+    fn restore_brings_back_slots_and_generations() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_clone::<u32>();
+        entities.register_component::<Frozen>();
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?.with_component(Frozen)?;
+        entities.create_entity().with_component(3_u32)?;
+        entities.delete_entity_by_id(0)?;
+        let snapshot = entities.snapshot()?;
+
+        entities.delete_entity_by_id(1)?;
+        entities.create_entity().with_component(4_u32)?;
+        entities.create_entity().with_component(5_u32)?;
+        entities.create_entity().with_component(6_u32)?;
+        *entities.get_mut::<u32>(2).unwrap() = 30;
+
+        entities.restore(&snapshot)?;
+
+        assert_eq!(entities.map, vec![0, 3, 1]);
+        assert_eq!(entities.generations, vec![1, 0, 0]);
+        assert_eq!(*entities.get::<u32>(2).unwrap(), 3);
+        assert!(entities.has::<Frozen>(1));
+        entities.create_entity().with_component(7_u32)?;
+        assert_eq!(entities.current_entity().unwrap().index(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn components_need_clone_functions() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+
+        assert!(matches!(entities.snapshot(), Err(Error::ComponentNotCloneable(_))));
+        Ok(())
+    }
+    #[test]
+    fn failed_restore_leaves_entities_alone() -> Result<()> {
+        let mut saved = Entites::default();
+        saved.register_clone::<u32>();
+        saved.register_clone::<f32>();
+        saved.create_entity().with_component(1_u32)?.with_component(1.0_f32)?;
+        let snapshot = saved.snapshot()?;
+
+        let mut entities = Entites::default();
+        entities.register_clone::<u32>();
+        entities.register_component::<f32>();
+        entities.create_entity().with_component(2_u32)?;
+        entities.create_entity().with_component(3_u32)?;
+
+        assert!(matches!(entities.restore(&snapshot), Err(Error::ComponentNotCloneable(_))));
+        assert_eq!(entities.map, vec![1, 1]);
+        assert_eq!(*entities.get::<u32>(1).unwrap(), 3);
+        Ok(())
+    }
+}
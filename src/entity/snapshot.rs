@@ -0,0 +1,460 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::{Component, Entites};
+use crate::entity::error::{Error, Result};
+
+const MAGIC: &[u8; 4] = b"ECSS";
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// Components that can be written into a binary snapshot.
+/// `decode` gets exactly the bytes written by `encode` and returns `None`
+/// when they are not a valid value.
+pub trait BinaryComponent: Any + Sized {
+    /// Length of every encoded value, `None` when it varies.
+    /// Snapshots record it so a world with a different encoding rejects them.
+    const ENCODED_SIZE: Option<usize> = None;
+
+    fn encode(&self, buf: &mut Vec<u8>);
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_binary_component {
+    ($($ty:ty),*) => {
+        $(
+            impl BinaryComponent for $ty {
+                const ENCODED_SIZE: Option<usize> = Some(std::mem::size_of::<$ty>());
+
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    Some(<$ty>::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_binary_component!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+/// Written as 8 bytes so snapshots move between 32 and 64 bit targets.
+impl BinaryComponent for usize {
+    const ENCODED_SIZE: Option<usize> = u64::ENCODED_SIZE;
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as u64).encode(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        u64::decode(bytes)?.try_into().ok()
+    }
+}
+
+impl BinaryComponent for isize {
+    const ENCODED_SIZE: Option<usize> = i64::ENCODED_SIZE;
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        (*self as i64).encode(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        i64::decode(bytes)?.try_into().ok()
+    }
+}
+
+impl BinaryComponent for bool {
+    const ENCODED_SIZE: Option<usize> = Some(1);
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.push(*self as u8);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0] => Some(false),
+            [1] => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl BinaryComponent for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+/// Written in place of the size of components whose encoded length varies.
+const VARIABLE_SIZE: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Codec {
+    pub name: &'static str,
+    pub size: Option<usize>,
+    pub encode: fn(&dyn Any, &mut Vec<u8>),
+    pub decode: fn(&[u8]) -> Option<Component>,
+}
+
+impl Codec {
+    pub fn of<T: BinaryComponent>() -> Self {
+        Self {
+            name: std::any::type_name::<T>(),
+            size: T::ENCODED_SIZE,
+            encode: encode_component::<T>,
+            decode: decode_component::<T>,
+        }
+    }
+}
+
+fn encode_component<T: BinaryComponent>(data: &dyn Any, buf: &mut Vec<u8>) {
+    if let Some(data) = data.downcast_ref::<T>() {
+        data.encode(buf);
+    }
+}
+
+fn decode_component<T: BinaryComponent>(bytes: &[u8]) -> Option<Component> {
+    let data = T::decode(bytes)?;
+    Some(Rc::new(RefCell::new(data)))
+}
+
+pub(crate) struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(Error::InvalidSnapshot("snapshot ended unexpectedly".to_string()));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+impl Entites {
+    pub fn register_codec<T: BinaryComponent>(&mut self) {
+        self.codecs.insert(TypeId::of::<T>(), Codec::of::<T>());
+    }
+
+    /// Layout:
+    /// magic, version, component table (name, encoded size, bit mask),
+    /// entity masks, then one column per component holding
+    /// length prefixed values for the entities that have it.
+    pub fn save_snapshot(&self) -> Vec<u8> {
+        let mut table = self.codecs
+            .iter()
+            .map(|(type_id, codec)| (*type_id, *codec, self.bit_masks[type_id]))
+            .collect::<Vec<_>>();
+        table.sort_by_key(|(_, _, bit_mask)| *bit_mask);
+        let snapshot_mask = table.iter().fold(0, |mask, (_, _, bit_mask)| mask | bit_mask);
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        buf.extend_from_slice(&(table.len() as u16).to_le_bytes());
+        for (_, codec, bit_mask) in &table {
+            buf.extend_from_slice(&(codec.name.len() as u16).to_le_bytes());
+            buf.extend_from_slice(codec.name.as_bytes());
+            let size = codec.size.map_or(VARIABLE_SIZE, |size| size as u32);
+            buf.extend_from_slice(&size.to_le_bytes());
+            buf.extend_from_slice(&bit_mask.to_le_bytes());
+        }
+
+        buf.extend_from_slice(&(self.map.len() as u32).to_le_bytes());
+        for mask in &self.map {
+            buf.extend_from_slice(&(mask & snapshot_mask).to_le_bytes());
+        }
+
+        let mut value = Vec::new();
+        for (type_id, codec, bit_mask) in &table {
+            for (index, mask) in self.map.iter().enumerate() {
+                if mask & bit_mask != *bit_mask {
+                    continue;
+                }
+                value.clear();
+                if let Some(component) = self.component_at(type_id, index) {
+                    (codec.encode)(&*component.borrow(), &mut value);
+                }
+                buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                buf.extend_from_slice(&value);
+            }
+        }
+
+        buf
+    }
+
+    /// Replaces every entity with the ones stored in the snapshot.
+    /// The whole snapshot is checked first, so a failed load changes nothing.
+    /// Generations are not stored and start over at 0.
+    /// Components are matched by type name, so the registration order may differ
+    /// from the world that saved it. Components without a codec, including
+    /// dynamic components, are cleared.
+    pub fn load_snapshot(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(Error::InvalidSnapshot("missing snapshot header".to_string()));
+        }
+        let version = reader.u16()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(Error::InvalidSnapshot(format!("unsupported snapshot version {version}")));
+        }
+
+        let component_count = reader.u16()?;
+        let mut table = Vec::with_capacity(component_count as usize);
+        for _ in 0..component_count {
+            let name_len = reader.u16()? as usize;
+            let name = std::str::from_utf8(reader.take(name_len)?)
+                .map_err(|_| Error::InvalidSnapshot("component name is not utf8".to_string()))?;
+            let size = match reader.u32()? {
+                VARIABLE_SIZE => None,
+                size => Some(size as usize),
+            };
+            let bit_mask = reader.u32()?;
+
+            let (type_id, codec) = self.codecs
+                .iter()
+                .find(|(_, codec)| codec.name == name)
+                .ok_or_else(|| Error::UnknownSnapshotComponent(format!("{name} is not registered with a codec")))?;
+            if codec.size != size {
+                return Err(Error::MismatchedSnapshotComponent(
+                    format!("{name} is encoded as {size:?} bytes in the snapshot but {:?} in the world", codec.size)
+                ));
+            }
+            if table.iter().any(|(other, _, _)| other == type_id) {
+                return Err(Error::InvalidSnapshot(format!("{name} is listed twice")));
+            }
+            table.push((*type_id, *codec, bit_mask));
+        }
+
+        let entity_count = reader.u32()? as usize;
+        if reader.bytes.len() / 4 < entity_count {
+            return Err(Error::InvalidSnapshot(format!("snapshot is too short for {entity_count} entities")));
+        }
+        let mut masks = Vec::with_capacity(entity_count);
+        for _ in 0..entity_count {
+            masks.push(reader.u32()?);
+        }
+
+        let mut decoded = Vec::new();
+        for (type_id, codec, bit_mask) in &table {
+            for (index, mask) in masks.iter().enumerate() {
+                if mask & bit_mask != *bit_mask {
+                    continue;
+                }
+                let len = reader.u32()? as usize;
+                if codec.size.is_some_and(|size| size != len) {
+                    return Err(Error::InvalidSnapshot(format!("{} value has the wrong length", codec.name)));
+                }
+                let component = (codec.decode)(reader.take(len)?)
+                    .ok_or_else(|| Error::InvalidSnapshot(format!("could not decode {}", codec.name)))?;
+                decoded.push((*type_id, index, component));
+            }
+        }
+        if !reader.bytes.is_empty() {
+            return Err(Error::InvalidSnapshot("trailing bytes after snapshot".to_string()));
+        }
+        for (type_id, codec, _bit_mask) in &table {
+            let holders = decoded.iter().filter(|(other, _, _)| other == type_id).count();
+            if self.is_unique(type_id) && holders > 1 {
+                return Err(Error::UniqueComponentTaken(format!("{holders} entities in the snapshot have {}", codec.name)));
+            }
+        }
+
+        for components in self.components.values_mut() {
+            *components = vec![None; entity_count];
+        }
+        for components in self.dynamic_components.values_mut() {
+            *components = vec![None; entity_count];
+        }
+        for sparse_set in self.sparse_sets.values_mut() {
+            sparse_set.clear();
+        }
+        self.clear_indexes();
+        for (index, mask) in self.map.iter().enumerate() {
+            self.record_removed(index, *mask);
+        }
+        self.map = vec![0; entity_count];
+        self.generations = vec![0; entity_count];
+        self.clear_archetypes();
+        for (type_id, index, component) in decoded {
+            self.insert_component(type_id, index, component)?;
+        }
+        self.inserting_into_index = 0;
+
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn snapshot_round_trip() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        entities.register_codec::<u32>();
+        entities.register_codec::<f32>();
+        entities.create_entity().with_component(10_u32)?.with_component(1.5_f32)?;
+        entities.create_entity().with_component(20_u32)?;
+
+        let bytes = entities.save_snapshot();
+        entities.delete_entity_by_id(0)?;
+        entities.create_entity().with_component(2.5_f32)?;
+        entities.load_snapshot(&bytes)?;
+
+        assert_eq!(entities.map, vec![3, 1]);
+        let u32s = &entities.components[&TypeId::of::<u32>()];
+        let second = u32s[1].as_ref().unwrap().borrow();
+        assert_eq!(*second.downcast_ref::<u32>().unwrap(), 20);
+        Ok(())
+    }
+
+    #[test]
+    fn components_are_matched_by_name_not_bit() -> Result<()> {
+        let mut saved = Entites::default();
+        saved.register_component::<u32>();
+        saved.register_component::<f32>();
+        saved.register_codec::<u32>();
+        saved.register_codec::<f32>();
+        saved.create_entity().with_component(1.5_f32)?;
+
+        let mut loaded = Entites::default();
+        loaded.register_component::<f32>();
+        loaded.register_component::<u32>();
+        loaded.register_codec::<f32>();
+        loaded.register_codec::<u32>();
+        loaded.load_snapshot(&saved.save_snapshot())?;
+
+        assert_eq!(loaded.map, vec![1]);
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_component_is_rejected() -> Result<()> {
+        let mut saved = Entites::default();
+        saved.register_component::<u32>();
+        saved.register_codec::<u32>();
+        saved.create_entity().with_component(1_u32)?;
+
+        let mut loaded = Entites::default();
+        loaded.register_component::<u32>();
+        let result = loaded.load_snapshot(&saved.save_snapshot());
+
+        assert!(matches!(result, Err(Error::UnknownSnapshotComponent(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn truncated_snapshot_is_rejected() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_codec::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+
+        let bytes = entities.save_snapshot();
+        let result = entities.load_snapshot(&bytes[..bytes.len() - 1]);
+
+        assert!(matches!(result, Err(Error::InvalidSnapshot(_))));
+        assert_eq!(entities.map, vec![1]);
+        Ok(())
+    }
+
+    #[test]
+    fn entity_count_larger_than_the_snapshot_is_rejected() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_codec::<u32>();
+
+        let mut bytes = entities.save_snapshot();
+        let count_at = bytes.len() - 4;
+        bytes[count_at..].copy_from_slice(&u32::MAX.to_le_bytes());
+        let result = entities.load_snapshot(&bytes);
+
+        assert!(matches!(result, Err(Error::InvalidSnapshot(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn sizes_are_the_encoded_lengths() -> Result<()> {
+        let mut saved = Entites::default();
+        saved.register_component::<usize>();
+        saved.register_component::<String>();
+        saved.register_codec::<usize>();
+        saved.register_codec::<String>();
+        saved.create_entity().with_component(7_usize)?.with_component("seven".to_string())?;
+
+        let mut loaded = Entites::default();
+        loaded.register_component::<usize>();
+        loaded.register_component::<String>();
+        loaded.register_codec::<usize>();
+        loaded.register_codec::<String>();
+        loaded.load_snapshot(&saved.save_snapshot())?;
+
+        assert_eq!(saved.codecs[&TypeId::of::<usize>()].size, Some(8));
+        assert_eq!(*loaded.component_at(&TypeId::of::<usize>(), 0).unwrap().borrow().downcast_ref::<usize>().unwrap(), 7);
+        Ok(())
+    }
+
+    #[test]
+    fn failed_loads_leave_entities_alone() -> Result<()> {
+        let mut saved = Entites::default();
+        saved.register_component::<u8>();
+        saved.register_codec::<u8>();
+        saved.create_entity().with_component(1_u8)?;
+        saved.create_entity().with_component(2_u8)?;
+
+        let mut entities = Entites::default();
+        entities.register_unique_component::<u8>();
+        entities.register_codec::<u8>();
+        entities.create_entity().with_component(3_u8)?;
+        entities.delete_entity_by_id(0)?;
+        entities.create_entity().with_component(4_u8)?;
+
+        let result = entities.load_snapshot(&saved.save_snapshot());
+
+        assert!(matches!(result, Err(Error::UniqueComponentTaken(_))));
+        assert_eq!(entities.map, vec![1]);
+        assert_eq!(entities.generations, vec![1]);
+        assert_eq!(*entities.get::<u8>(0).unwrap(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn generations_start_over() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_codec::<u32>();
+        entities.create_entity().with_component(1_u32)?;
+        let bytes = entities.save_snapshot();
+        entities.delete_entity_by_id(0)?;
+        entities.create_entity().with_component(2_u32)?;
+        entities.create_entity().with_component(3_u32)?;
+
+        entities.load_snapshot(&bytes)?;
+
+        assert_eq!(entities.generations, vec![0]);
+        Ok(())
+    }
+}
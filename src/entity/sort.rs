@@ -0,0 +1,105 @@
+use std::any::Any;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use super::query::{Query, QueryItems};
+use crate::entity::error::Result;
+
+impl Query<'_> {
+    /// Query items ordered by a key read from component `T`.
+    /// Entities with equal keys keep their index order.
+    pub fn sort_by_key<T: Any, K: Ord>(&self, f: impl Fn(&T) -> K) -> Result<QueryItems> {
+        let column = self.column_of::<T>()?;
+        let mut items = self.run_entities()
+            .into_iter()
+            .map(|(entity, components)| {
+                let key = f(components[column].borrow().downcast_ref::<T>().unwrap());
+                (key, (entity, components))
+            })
+            .collect::<Vec<_>>();
+        items.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(items.into_iter().map(|(_key, item)| item).collect())
+    }
+
+    /// Query items ordered by comparing component `T` of two entities.
+    pub fn sort_by<T: Any>(&self, mut compare: impl FnMut(&T, &T) -> Ordering) -> Result<QueryItems> {
+        let column = self.column_of::<T>()?;
+        let mut items = self.run_entities();
+        items.sort_by(|(_, a), (_, b)| {
+            let (a, b) = (a[column].borrow(), b[column].borrow());
+            compare(a.downcast_ref::<T>().unwrap(), b.downcast_ref::<T>().unwrap())
+        });
+        Ok(items)
+    }
+
+    /// Query items grouped by a key read from component `T`, groups ordered by key.
+    pub fn group_by<T: Any, K: Ord>(&self, f: impl Fn(&T) -> K) -> Result<Vec<(K, QueryItems)>> {
+        let column = self.column_of::<T>()?;
+        let mut groups = BTreeMap::<K, QueryItems>::new();
+        for (entity, components) in self.run_entities() {
+            let key = f(components[column].borrow().downcast_ref::<T>().unwrap());
+            groups.entry(key).or_default().push((entity, components));
+        }
+        Ok(groups.into_iter().collect())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::Entites;
+    use anyhow::Result;
+
+    fn initialize_entities() -> Result<Entites> {
+        let mut entities = Entites::default();
+        entities.register_component::<u32>();
+        entities.register_component::<f32>();
+        for (depth, priority) in [(3_u32, 0.5_f32), (1, 2.5), (2, 0.5), (1, 1.5)] {
+            entities.create_entity().with_component(depth)?.with_component(priority)?;
+        }
+        Ok(entities)
+    }
+
+    fn indexes(items: &QueryItems) -> Vec<usize> {
+        items.iter().map(|(entity, _)| entity.index()).collect()
+    }
+
+    #[test]
+    fn sort_by_key_is_stable() -> Result<()> {
+        let entities = initialize_entities()?;
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?;
+
+        let items = query.sort_by_key::<u32, _>(|depth| *depth)?;
+        assert_eq!(indexes(&items), vec![1, 3, 2, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn sort_by_compares_components() -> Result<()> {
+        let entities = initialize_entities()?;
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?.with_component::<f32>()?;
+
+        let items = query.sort_by::<f32>(|a, b| b.total_cmp(a))?;
+        assert_eq!(indexes(&items), vec![1, 3, 0, 2]);
+        assert!(query.sort_by::<bool>(|_, _| Ordering::Equal).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn group_by_collects_equal_keys() -> Result<()> {
+        let entities = initialize_entities()?;
+        let mut query = Query::new(&entities);
+        query.with_component::<u32>()?;
+
+        let groups = query.group_by::<u32, _>(|depth| *depth)?;
+        let groups = groups
+            .iter()
+            .map(|(depth, items)| (*depth, indexes(items)))
+            .collect::<Vec<_>>();
+        assert_eq!(groups, vec![(1, vec![1, 3]), (2, vec![2]), (3, vec![0])]);
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use super::Component;
+
+/// Component storage for components that are added and removed often.
+/// Only entities that have the component take up space in `dense`,
+/// and both insert and remove are constant time.
+#[derive(Default, Debug)]
+pub struct SparseSet {
+    sparse: Vec<Option<usize>>,
+    dense: Vec<usize>,
+    data: Vec<Component>,
+}
+
+impl SparseSet {
+    pub fn insert(&mut self, index: usize, component: Component) {
+        if let Some(slot) = self.sparse.get(index).copied().flatten() {
+            self.data[slot] = component;
+            return;
+        }
+        if self.sparse.len() <= index {
+            self.sparse.resize(index + 1, None);
+        }
+        self.sparse[index] = Some(self.dense.len());
+        self.dense.push(index);
+        self.data.push(component);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Component> {
+        let slot = self.sparse.get_mut(index)?.take()?;
+        self.dense.swap_remove(slot);
+        let component = self.data.swap_remove(slot);
+        if let Some(moved) = self.dense.get(slot) {
+            self.sparse[*moved] = Some(slot);
+        }
+        Some(component)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Component> {
+        let slot = self.sparse.get(index).copied().flatten()?;
+        self.data.get(slot)
+    }
+
+    /// Entities that have the component, in no particular order.
+    pub fn entities(&self) -> &[usize] {
+        &self.dense
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.sparse.clear();
+        self.dense.clear();
+        self.data.clear();
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn value(set: &SparseSet, index: usize) -> Option<u32> {
+        set.get(index).map(|component| *component.borrow().downcast_ref::<u32>().unwrap())
+    }
+
+    #[test]
+    fn insert_get_and_remove() {
+        let mut set = SparseSet::default();
+        set.insert(5, Rc::new(RefCell::new(50_u32)));
+        set.insert(2, Rc::new(RefCell::new(20_u32)));
+        set.insert(9, Rc::new(RefCell::new(90_u32)));
+
+        assert_eq!(value(&set, 2), Some(20));
+        assert_eq!(value(&set, 3), None);
+
+        assert!(set.remove(5).is_some());
+        assert!(set.remove(5).is_none());
+        assert_eq!(set.len(), 2);
+        assert_eq!(value(&set, 9), Some(90));
+        assert_eq!(value(&set, 2), Some(20));
+    }
+
+    #[test]
+    fn insert_twice_replaces_value() {
+        let mut set = SparseSet::default();
+        set.insert(1, Rc::new(RefCell::new(1_u32)));
+        set.insert(1, Rc::new(RefCell::new(2_u32)));
+
+        assert_eq!(set.entities(), &[1]);
+        assert_eq!(value(&set, 1), Some(2));
+    }
+}
@@ -0,0 +1,418 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use super::handle::Entity;
+use super::hierarchy::Parent;
+use super::{Component, Entites, StorageType};
+use crate::entity::error::{Error, Result};
+
+/// Where the entities of a copy or move ended up: source index to destination.
+#[derive(Debug, Default, Clone)]
+pub struct EntityMap {
+    entities: HashMap<usize, (Entity, Entity)>,
+}
+
+impl EntityMap {
+    /// Destination index of the entity that was at `index` in the source.
+    pub fn get(&self, index: usize) -> Option<usize> {
+        self.entities.get(&index).map(|(_source, destination)| destination.index())
+    }
+
+    /// Destination handle for a source handle. Stale source handles map to nothing.
+    pub fn get_entity(&self, entity: Entity) -> Option<Entity> {
+        self.entities
+            .get(&entity.index())
+            .filter(|(source, _destination)| *source == entity)
+            .map(|(_source, destination)| *destination)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Pairs of source and destination handles, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.entities.values().copied()
+    }
+}
+
+/// Components holding entity indexes or handles implement this so a copy or move
+/// can point them at the new entities. Returning `false` means the component has
+/// nothing left to point at, and it is removed from the entity.
+pub trait MapEntities {
+    fn map_entities(&mut self, map: &EntityMap) -> bool;
+}
+
+/// Handles to entities that were not transferred are kept; they are simply not alive there.
+impl MapEntities for Entity {
+    fn map_entities(&mut self, map: &EntityMap) -> bool {
+        if let Some(entity) = map.get_entity(*self) {
+            *self = entity;
+        }
+        true
+    }
+}
+
+pub type CloneFn = fn(&dyn Any) -> Component;
+pub type MapEntitiesFn = fn(&mut dyn Any, &EntityMap) -> bool;
+pub(crate) type RegisterFn = fn(&mut Entites, StorageType) -> Result<()>;
+
+/// What another `Entites` needs to take over a component type.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TransferFns {
+    pub(crate) register: RegisterFn,
+    pub(crate) clone: Option<CloneFn>,
+    map_entities: Option<MapEntitiesFn>,
+    name: &'static str,
+    size: usize,
+}
+
+impl TransferFns {
+    pub(crate) fn of<T: Any>() -> Self {
+        Self {
+            register: register_component::<T>,
+            clone: None,
+            map_entities: None,
+            name: std::any::type_name::<T>(),
+            size: std::mem::size_of::<T>(),
+        }
+    }
+}
+
+pub(crate) fn register_component<T: Any>(entities: &mut Entites, storage: StorageType) -> Result<()> {
+    entities.try_register_component_with_storage::<T>(storage)
+}
+
+pub(crate) fn clone_component<T: Any + Clone>(data: &dyn Any) -> Component {
+    Rc::new(RefCell::new(data.downcast_ref::<T>().unwrap().clone()))
+}
+
+fn map_component<T: Any + MapEntities>(data: &mut dyn Any, map: &EntityMap) -> bool {
+    data.downcast_mut::<T>().unwrap().map_entities(map)
+}
+
+/// Components of one entity on their way to another `Entites`.
+struct Transferred {
+    source: usize,
+    components: Vec<(TypeId, Component)>,
+    dynamic: Vec<(String, Component)>,
+}
+
+impl Entites {
+    /// Lets copies of entities take a copy of their `T`.
+    pub fn register_clone<T: Any + Clone>(&mut self) {
+        self.register_component::<T>();
+        self.transfer_fns.get_mut(&TypeId::of::<T>()).unwrap().clone = Some(clone_component::<T>);
+    }
+
+    /// Lets copies and moves point the entity references inside `T` at the new entities.
+    pub fn register_map_entities<T: Any + MapEntities>(&mut self) {
+        self.register_component::<T>();
+        self.transfer_fns.get_mut(&TypeId::of::<T>()).unwrap().map_entities = Some(map_component::<T>);
+    }
+
+    /// For components that need more than `register_component_with_storage` to be set up.
+    pub(crate) fn set_register_fn(&mut self, type_id: &TypeId, register: RegisterFn) {
+        self.transfer_fns.get_mut(type_id).unwrap().register = register;
+    }
+
+    /// Id, name and size of every registered component type, including the ones
+    /// registered on the way by prefabs and transfers.
+    pub(crate) fn registered_types(&self) -> impl Iterator<Item = (TypeId, &'static str, usize)> + '_ {
+        self.transfer_fns.iter().map(|(type_id, fns)| (*type_id, fns.name, fns.size))
+    }
+
+    pub fn is_cloneable(&self, type_id: &TypeId) -> bool {
+        self.transfer_fns.get(type_id).is_some_and(|fns| fns.clone.is_some())
+    }
+
+    /// Copies the entities and all their descendants into `destination`.
+    /// Every component involved has to be registered with `register_clone`.
+    /// Entities copied without their parent become roots.
+    pub fn copy_entities_to(&self, roots: &[usize], destination: &mut Entites) -> Result<EntityMap> {
+        let entities = self.transfer_set(roots)?
+            .into_iter()
+            .map(|index| self.take_components(index, true))
+            .collect::<Result<Vec<_>>>()?;
+        let map = destination.receive(self, entities)?;
+        destination.map_received(&map)?;
+        Ok(map)
+    }
+
+    /// Like `copy_entities_to`, but the components themselves are handed over,
+    /// so they do not need to be cloneable. The entities are deleted here afterwards.
+    pub fn move_entities_to(&mut self, roots: &[usize], destination: &mut Entites) -> Result<EntityMap> {
+        let moved = self.transfer_set(roots)?;
+        let entities = moved
+            .iter()
+            .map(|index| self.take_components(*index, false))
+            .collect::<Result<Vec<_>>>()?;
+        let map = destination.receive(self, entities)?;
+        self.forget_moved(&moved)?;
+        destination.map_received(&map)?;
+        Ok(map)
+    }
+
+    /// Deletes entities whose components now belong to another `Entites`.
+    /// Unlike `delete_entity_by_id` this never writes to their components,
+    /// which would be visible on the other side.
+    fn forget_moved(&mut self, moved: &[usize]) -> Result<()> {
+        let moving = moved.iter().copied().collect::<HashSet<_>>();
+        for index in moved {
+            if let Some(parent) = self.parent(*index).filter(|parent| !moving.contains(parent)) {
+                self.remove_child(parent, *index)?;
+            }
+        }
+        for index in moved {
+            self.set_mask(*index, 0);
+            self.release_components(*index);
+        }
+        for index in moved {
+            self.remove_relations_targeting(*index);
+        }
+        Ok(())
+    }
+
+    /// The roots and everything below them, each entity once.
+    fn transfer_set(&self, roots: &[usize]) -> Result<Vec<usize>> {
+        let mut seen = HashSet::new();
+        let mut indexes = vec![];
+        for root in roots {
+            if self.entity_handle(*root).is_none() {
+                return Err(Error::EntityDoesNotExist(format!("entity {root} does not exist")));
+            }
+            for index in self.depth_first(*root) {
+                if seen.insert(index) {
+                    indexes.push(index);
+                }
+            }
+        }
+        Ok(indexes)
+    }
+
+    fn take_components(&self, index: usize, copy: bool) -> Result<Transferred> {
+        let mut components = self.components_of(index)?;
+        components.sort_by_key(|(type_id, _component)| self.bit_masks[type_id]);
+        if copy {
+            components = components
+                .into_iter()
+                .map(|(type_id, component)| {
+                    let clone = self.transfer_fns.get(&type_id).and_then(|fns| fns.clone).ok_or_else(|| {
+                        Error::ComponentNotCloneable(format!("entity {index} has a component without a clone function"))
+                    })?;
+                    Ok((type_id, clone(&*component.borrow())))
+                })
+                .collect::<Result<_>>()?;
+        }
+
+        let dynamic = self.dynamic_components_of(index)
+            .into_iter()
+            .map(|id| {
+                let name = self.dynamic_component_name(id).unwrap().to_string();
+                let component = self.dynamic_components[&id][index].clone().unwrap();
+                let component: Component = if copy {
+                    Rc::new(RefCell::new(component.borrow().downcast_ref::<Vec<u8>>().unwrap().clone()))
+                } else {
+                    component
+                };
+                (name, component)
+            })
+            .collect();
+
+        Ok(Transferred { source: index, components, dynamic })
+    }
+
+    /// Registers what is missing and creates the entities.
+    fn receive(&mut self, source: &Entites, mut entities: Vec<Transferred>) -> Result<EntityMap> {
+        let moved = entities.iter().map(|entity| entity.source).collect::<HashSet<_>>();
+        for entity in &mut entities {
+            entity.components.retain(|(type_id, component)| {
+                *type_id != TypeId::of::<Parent>()
+                    || moved.contains(&component.borrow().downcast_ref::<Parent>().unwrap().get())
+            });
+        }
+
+        let type_ids = entities
+            .iter()
+            .flat_map(|entity| entity.components.iter().map(|(type_id, _component)| *type_id))
+            .collect::<HashSet<_>>();
+        for type_id in &type_ids {
+            self.adopt_component(source, type_id)?;
+            if !self.is_unique(type_id) {
+                continue;
+            }
+            let holders = entities
+                .iter()
+                .filter(|entity| entity.components.iter().any(|(other, _component)| other == type_id))
+                .count();
+            if holders > 1 {
+                return Err(Error::UniqueComponentTaken(format!("{holders} entities would share a unique component")));
+            }
+            self.check_unique(type_id, usize::MAX, self.bit_masks[type_id])?;
+        }
+        for entity in &entities {
+            for (name, _component) in &entity.dynamic {
+                self.register_dynamic_component(name)?;
+            }
+        }
+
+        let mut map = EntityMap::default();
+        for entity in entities {
+            if entity.components.is_empty() && entity.dynamic.is_empty() {
+                continue;
+            }
+            self.create_entity();
+            let index = self.inserting_into_index;
+            for (type_id, component) in entity.components {
+                self.insert_component(type_id, index, component)?;
+            }
+            for (name, component) in entity.dynamic {
+                let id = self.dynamic_component_id(&name).unwrap();
+                self.dynamic_components.get_mut(&id).unwrap()[index] = Some(component);
+                self.add_to_mask(index, self.dynamic_bit_masks[&id]);
+            }
+            let handles = (source.entity_handle(entity.source).unwrap(), self.entity_handle(index).unwrap());
+            map.entities.insert(entity.source, handles);
+        }
+        Ok(map)
+    }
+
+    /// Points the entity references of the received entities at their new indexes.
+    fn map_received(&mut self, map: &EntityMap) -> Result<()> {
+        for (_source, entity) in map.iter() {
+            let index = entity.index();
+            for (type_id, component) in self.components_of(index)? {
+                if let Some(map_entities) = self.transfer_fns.get(&type_id).and_then(|fns| fns.map_entities) {
+                    let keep = map_entities(&mut *component.borrow_mut(), map);
+                    if keep {
+                        self.component_mutated(&type_id, index);
+                    } else {
+                        self.delete_component(type_id, index)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Registers `type_id` the way `source` has it, unless it is registered already.
+    fn adopt_component(&mut self, source: &Entites, type_id: &TypeId) -> Result<()> {
+        if !self.bit_masks.contains_key(type_id) {
+            let storage = source.storage_type(type_id).unwrap_or_default();
+            (source.transfer_fns[type_id].register)(self, storage)?;
+            if let Some(codec) = source.codecs.get(type_id) {
+                self.codecs.insert(*type_id, *codec);
+            }
+            if source.is_unique(type_id) {
+                self.unique.insert(*type_id);
+            }
+        }
+        let fns = source.transfer_fns[type_id];
+        let own = self.transfer_fns.get_mut(type_id).unwrap();
+        own.clone = own.clone.or(fns.clone);
+        own.map_entities = own.map_entities.or(fns.map_entities);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Target(Entity);
+
+    impl MapEntities for Target {
+        fn map_entities(&mut self, map: &EntityMap) -> bool {
+            self.0.map_entities(map)
+        }
+    }
+
+    fn initialize_entities() -> Result<Entites> {
+        let mut entities = Entites::default();
+        entities.register_clone::<u32>();
+        entities.register_clone::<Target>();
+        entities.register_map_entities::<Target>();
+        entities.register_component::<f32>();
+
+        entities.create_entity().with_component(1_u32)?;
+        entities.create_entity().with_component(2_u32)?;
+        entities.create_entity().with_component(3_u32)?;
+        let target = entities.entity_handle(2).unwrap();
+        entities.add_component_to_entity_by_id(Target(target), 1)?;
+        entities.add_child(1, 2)?;
+        Ok(entities)
+    }
+
+    #[test]
+    fn copies_remap_hierarchy_and_references() -> Result<()> {
+        let source = initialize_entities()?;
+        let mut destination = Entites::default();
+        destination.register_component::<bool>();
+        destination.create_entity().with_component(true)?;
+
+        let map = source.copy_entities_to(&[1], &mut destination)?;
+
+        assert_eq!(map.len(), 2);
+        let (copy, child) = (map.get(1).unwrap(), map.get(2).unwrap());
+        assert_eq!((copy, child), (1, 2));
+        assert_eq!(destination.children(copy), vec![child]);
+        assert_eq!(destination.parent(child), Some(copy));
+        assert_eq!(destination.get::<Target>(copy).unwrap().0, destination.entity_handle(child).unwrap());
+        assert_eq!(*destination.get::<u32>(child).unwrap(), 3);
+        assert_eq!(*source.get::<u32>(1).unwrap(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn moving_a_child_detaches_it() -> Result<()> {
+        let mut source = initialize_entities()?;
+        let mut destination = Entites::default();
+
+        let map = source.move_entities_to(&[2], &mut destination)?;
+
+        let moved = map.get(2).unwrap();
+        assert_eq!(destination.parent(moved), None);
+        assert_eq!(*destination.get::<u32>(moved).unwrap(), 3);
+        assert!(source.entity_handle(2).is_none());
+        assert!(source.children(1).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn copies_need_clone_functions() -> Result<()> {
+        let mut source = initialize_entities()?;
+        source.add_component_to_entity_by_id(1.0_f32, 0)?;
+        let mut destination = Entites::default();
+
+        assert!(matches!(source.copy_entities_to(&[0], &mut destination), Err(Error::ComponentNotCloneable(_))));
+        assert!(destination.entity_handle(0).is_none());
+
+        source.move_entities_to(&[0], &mut destination)?;
+        assert_eq!(*destination.get::<f32>(0).unwrap(), 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn full_destinations_report_too_many_components() -> Result<()> {
+        let mut source = initialize_entities()?;
+        let mut destination = Entites::default();
+        for bit in 0..u32::BITS {
+            destination.register_dynamic_component(&bit.to_string())?;
+        }
+
+        assert!(matches!(source.copy_entities_to(&[0], &mut destination), Err(Error::TooManyComponents(_))));
+        assert!(matches!(source.move_entities_to(&[0], &mut destination), Err(Error::TooManyComponents(_))));
+        assert!(destination.entity_handle(0).is_none());
+        assert_eq!(*source.get::<u32>(0).unwrap(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefMut};
+
+use super::handle::Entity;
+use super::{Entites, StorageType};
+use crate::entity::error::{Error, Result};
+
+impl Entites {
+    /// Registers `T` so that at most one entity can have it at a time.
+    /// Giving it to a second entity fails with `Error::UniqueComponentTaken`.
+    pub fn register_unique_component<T: Any>(&mut self) {
+        self.register_component_with_storage::<T>(StorageType::Table);
+        self.unique.insert(TypeId::of::<T>());
+    }
+
+    pub fn is_unique(&self, type_id: &TypeId) -> bool {
+        self.unique.contains(type_id)
+    }
+
+    /// Entities that have every component in `mask`, found through the archetypes.
+    pub(crate) fn holders_of(&self, mask: u32) -> Vec<usize> {
+        let mut holders = self.archetypes
+            .iter()
+            .filter(|archetype| archetype.mask() & mask == mask)
+            .flat_map(|archetype| archetype.entities().copied())
+            .collect::<Vec<_>>();
+        holders.sort_unstable();
+        holders
+    }
+
+    /// Fails when `type_id` is unique and an entity other than `index` already has it.
+    pub(crate) fn check_unique(&self, type_id: &TypeId, index: usize, mask: u32) -> Result<()> {
+        if !self.is_unique(type_id) {
+            return Ok(());
+        }
+        match self.holders_of(mask).into_iter().find(|holder| *holder != index) {
+            Some(holder) => Err(Error::UniqueComponentTaken(
+                format!("entity {holder} already has this unique component, entity {index} cannot get it")
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// The only entity with `T` and its value.
+    pub fn single<T: Any>(&self) -> Result<(Entity, Ref<'_, T>)> {
+        let index = self.single_holder::<T>()?;
+        Ok((self.entity_handle(index).unwrap(), self.get::<T>(index).unwrap()))
+    }
+
+    /// Fails with `TagNotMutable` for tags, which have no value to change.
+    pub fn single_mut<T: Any>(&mut self) -> Result<(Entity, RefMut<'_, T>)> {
+        self.ensure_not_tag::<T>()?;
+        let index = self.single_holder::<T>()?;
+        let entity = self.entity_handle(index).unwrap();
+        Ok((entity, self.get_mut::<T>(index).unwrap()))
+    }
+
+    fn single_holder<T: Any>(&self) -> Result<usize> {
+        let mask = self.get_bitmask(&TypeId::of::<T>()).ok_or_else(|| {
+            Error::ComponetNotRegister(format!("{} is not registered", std::any::type_name::<T>()))
+        })?;
+        match self.holders_of(mask).as_slice() {
+            [index] => Ok(*index),
+            holders => Err(Error::NotExactlyOneMatch(
+                format!("{} entities have {}", holders.len(), std::any::type_name::<T>())
+            )),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+
+    struct Player;
+
+    #[derive(Debug, PartialEq)]
+    struct MainCamera(f32);
+
+    #[test]
+    fn second_holder_is_rejected() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_unique_component::<Player>();
+        entities.register_component::<u32>();
+
+        entities.create_entity().with_component(Player)?.with_component(1_u32)?;
+        entities.add_component_to_entity_by_id(Player, 0)?;
+
+        entities.create_entity().with_component(2_u32)?;
+        assert!(matches!(entities.with_component(Player), Err(Error::UniqueComponentTaken(_))));
+        assert!(matches!(entities.add_component_to_entity_by_id(Player, 1), Err(Error::UniqueComponentTaken(_))));
+
+        entities.delete_component_by_entity_id::<Player>(0)?;
+        entities.add_component_to_entity_by_id(Player, 1)?;
+        assert_eq!(entities.single::<Player>()?.0.index(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn single_needs_exactly_one_holder() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_unique_component::<MainCamera>();
+        assert!(matches!(entities.single::<MainCamera>(), Err(Error::NotExactlyOneMatch(_))));
+
+        entities.create_entity().with_component(MainCamera(1.0))?;
+        entities.single_mut::<MainCamera>()?.1.0 = 2.0;
+
+        let (camera, value) = entities.single::<MainCamera>()?;
+        assert_eq!(camera.index(), 0);
+        assert_eq!(*value, MainCamera(2.0));
+        Ok(())
+    }
+
+    #[test]
+    fn single_mut_rejects_tags() -> Result<()> {
+        let mut entities = Entites::default();
+        entities.register_unique_component::<Player>();
+        entities.create_entity().with_component(Player)?;
+
+        assert_eq!(entities.single::<Player>()?.0.index(), 0);
+        assert!(matches!(entities.single_mut::<Player>(), Err(Error::TagNotMutable(_))));
+        Ok(())
+    }
+}
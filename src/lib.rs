@@ -1,18 +1,96 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
+use std::cell::{Ref, RefMut};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::hash::Hash;
+use std::ops::RangeBounds;
+use std::rc::Rc;
 
 
 mod resources;
 mod entity;
+mod registry;
+pub mod transform;
+pub mod spatial;
+pub mod scene;
+pub mod asset;
+pub mod delta;
 
-use crate::entity::query::Query;
-use entity::{Entites, Result};
+use crate::entity::query::{Query, QueryComponents, QueryIndexes, QueryItems};
+pub use entity::query_state::QueryState;
+pub use entity::handle::Entity;
+pub use entity::join::{EntityReference, JoinItems, JoinTarget};
+pub use entity::index::IndexKind;
+pub use entity::name::Name;
+pub use entity::transfer::{EntityMap, MapEntities};
+pub use entity::prefab::Prefab;
+pub use entity::rollback::WorldSnapshot;
+pub use entity::delta::EntitesDelta;
+pub use delta::WorldDelta;
+use entity::Entites;
+pub use entity::{Error, Result, StorageType};
+pub use entity::snapshot::BinaryComponent;
+pub use entity::dynamic::ComponentId;
+pub use entity::hierarchy::{Ancestors, Children, DepthFirst, Descendants, Parent};
+pub use entity::relation::Relation;
+pub use entity::entity_ref::{EntityMut, EntityRef};
 use resources::Resource;
+pub use registry::{Reflect, TypeInfo, TypeRegistry};
 
 
-#[derive(Default, Debug)]
+#[derive(Default)]
 pub struct World {
     resources: Resource,
     entities: Entites,
+    registry: TypeRegistry,
+    snapshot_resources: HashMap<TypeId, ResourceCloneFn>,
+    resource_codecs: delta::ResourceCodecs,
+}
+
+type ResourceCloneFn = fn(&dyn Any) -> Box<dyn Any>;
+
+fn clone_resource<T: Any + Clone>(data: &dyn Any) -> Box<dyn Any> {
+    Box::new(data.downcast_ref::<T>().unwrap().clone())
+}
+
+/// Lists every entity by name, or `#index` when it has none, with the
+/// registered components it holds, followed by the resources.
+impl Debug for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("World")
+            .field("entities", &DebugEntities(self))
+            .field("resources", &self.resources)
+            .finish()
+    }
+}
+
+struct DebugEntities<'a>(&'a World);
+
+impl Debug for DebugEntities<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let world = self.0;
+        let mut entities = f.debug_map();
+        for index in world.entities.iter_entities().map(|entity| entity.index()) {
+            let components = world.debug_entity(index).unwrap_or_default();
+            entities.entry(
+                &format_args!("{}", world.entities.entity_label(index)),
+                &DebugComponents(&components),
+            );
+        }
+        entities.finish()
+    }
+}
+
+struct DebugComponents<'a>(&'a [(&'static str, String)]);
+
+impl Debug for DebugComponents<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut components = f.debug_map();
+        for (name, value) in self.0 {
+            components.entry(&format_args!("{name}"), &format_args!("{value}"));
+        }
+        components.finish()
+    }
 }
 
 impl World {
@@ -82,17 +160,174 @@ impl World {
 
     pub fn register_component<T:Any +'static >(&mut self) {
         self.entities.register_component::<T>();
+        self.sync_registry();
     }
 
 
+    /// Picks the storage for a component. Use `StorageType::SparseSet` for
+    /// components that are added and removed often; queries can mix both kinds.
+    /// ```
+    /// use::ecs_library::{StorageType, World};
+    /// struct Selected;
+    /// let mut world = World::new();
+    /// world.register_component::<u32>();
+    /// world.register_component_with_storage::<Selected>(StorageType::SparseSet);
+    /// world.create_entity().with_component(1_u32).unwrap();
+    /// world.create_entity().with_component(2_u32).unwrap().with_component(Selected).unwrap();
+    /// let query = world.query()
+    ///     .with_component::<u32>().unwrap()
+    ///     .with_component::<Selected>().unwrap()
+    ///     .run();
+    /// assert_eq!(query.0, vec![1]);
+    /// ```
+    pub fn register_component_with_storage<T:Any>(&mut self, storage: StorageType) {
+        self.entities.register_component_with_storage::<T>(storage);
+        self.sync_registry();
+    }
+
+    /// Fails with `TooManyComponents` instead of panicking once
+    /// the 32 bits of the entity map are taken.
+    pub fn try_register_component_with_storage<T:Any>(&mut self, storage: StorageType) -> Result<()> {
+        self.entities.try_register_component_with_storage::<T>(storage)?;
+        self.sync_registry();
+        Ok(())
+    }
+
+    /// Registers a component that at most one entity can have, like the player.
+    /// ```
+    /// use::ecs_library::World;
+    /// struct Player;
+    /// let mut world = World::new();
+    /// world.register_unique_component::<Player>();
+    /// world.create_entity().with_component(Player).unwrap();
+    /// assert!(world.create_entity().with_component(Player).is_err());
+    /// assert_eq!(world.single::<Player>().unwrap().0.index(), 0);
+    /// ```
+    pub fn register_unique_component<T:Any>(&mut self) {
+        self.entities.register_unique_component::<T>();
+        self.registry.register::<T>();
+    }
+
+    pub fn single<T:Any>(&self) -> Result<(Entity, Ref<'_, T>)> {
+        self.entities.single::<T>()
+    }
+
+    pub fn single_mut<T:Any>(&mut self) -> Result<(Entity, RefMut<'_, T>)> {
+        self.entities.single_mut::<T>()
+    }
+
     pub fn create_entity(&mut self) -> &mut Entites {
         self.entities.create_entity()
     }
 
-    pub fn query(&self) -> Query{
+    /// Reads one component of one entity. The value is behind a `RefCell`
+    /// like query results are, so it comes back as a `Ref`.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// world.register_component::<u32>();
+    /// world.create_entity().with_component(10_u32).unwrap();
+    /// *world.get_mut::<u32>(0).unwrap() += 1;
+    /// assert_eq!(*world.get::<u32>(0).unwrap(), 11);
+    /// assert!(world.has::<u32>(0));
+    /// ```
+    pub fn get<T:Any>(&self, index:usize) -> Option<Ref<'_, T>> {
+        self.entities.get::<T>(index)
+    }
+
+    pub fn get_mut<T:Any>(&mut self, index:usize) -> Option<RefMut<'_, T>> {
+        self.entities.get_mut::<T>(index)
+    }
+
+    pub fn has<T:Any>(&self, index:usize) -> bool {
+        self.entities.has::<T>(index)
+    }
+
+    /// Mutable access to `T` on several distinct entities at once.
+    pub fn get_many_mut<T:Any>(&mut self, indexes:&[usize]) -> Result<Vec<RefMut<'_, T>>> {
+        self.entities.get_many_mut::<T>(indexes)
+    }
+
+    pub fn entity(&self, index:usize) -> Result<EntityRef<'_>> {
+        self.entities.entity(index)
+    }
+
+    pub fn entity_mut(&mut self, index:usize) -> Result<EntityMut<'_>> {
+        self.entities.entity_mut(index)
+    }
+
+    pub fn add_hash_index<T:Any + Debug + Hash + Eq + Clone>(&mut self) -> Result<()> {
+        self.entities.add_hash_index::<T>()
+    }
+
+    pub fn add_ordered_index<T:Any + Debug + Ord + Clone>(&mut self) -> Result<()> {
+        self.entities.add_ordered_index::<T>()
+    }
+
+    pub fn lookup<T:Any>(&self, key:&T) -> Result<Vec<Entity>> {
+        self.entities.lookup(key)
+    }
+
+    pub fn lookup_range<T:Any + Ord>(&self, range: impl RangeBounds<T>) -> Result<Vec<Entity>> {
+        self.entities.lookup_range(range)
+    }
+
+    pub fn reindex<T:Any>(&mut self) -> Result<()> {
+        self.entities.reindex::<T>()
+    }
+
+    /// Increases with every recorded component change; see `changed_since`.
+    pub fn change_tick(&self) -> u64 {
+        self.entities.change_tick()
+    }
+
+    /// Entities whose `T` was added, changed or removed after `tick`.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// world.register_component::<u32>();
+    /// world.create_entity().with_component(1_u32).unwrap();
+    /// world.create_entity().with_component(2_u32).unwrap();
+    /// let tick = world.change_tick();
+    /// *world.get_mut::<u32>(1).unwrap() += 1;
+    /// assert_eq!(world.changed_since::<u32>(tick), vec![1]);
+    /// ```
+    pub fn changed_since<T:Any>(&self, tick:u64) -> Vec<usize> {
+        self.entities.changed_since::<T>(tick)
+    }
+
+    /// Needed after writing to `T` through components returned by a query.
+    pub fn mark_changed<T:Any>(&self, index:usize) {
+        self.entities.mark_changed::<T>(index)
+    }
+
+    pub fn query(&self) -> Query<'_>{
         Query::new(&self.entities)
     }
 
+    /// Runs a cached query. Build the state once with `Query::state` and keep it
+    /// around; later runs only pay for the entities that match.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// world.register_component::<u32>();
+    /// let mut state = world.query().with_component::<u32>().unwrap().state();
+    /// world.create_entity().with_component(10_u32).unwrap();
+    /// let (indexes, _components) = world.run_query_state(&mut state);
+    /// assert_eq!(indexes, vec![0]);
+    /// ```
+    pub fn run_query_state(&self, state: &mut QueryState) -> (QueryIndexes, QueryComponents) {
+        state.run(&self.entities)
+    }
+
+    pub fn run_query_state_entities(&self, state: &mut QueryState) -> QueryItems {
+        state.run_entities(&self.entities)
+    }
+
+    pub fn count_query_state(&self, state: &mut QueryState) -> usize {
+        state.count(&self.entities)
+    }
+
     pub fn delete_component_by_entity_id<T:Any>(&mut self, index:usize) -> Result<()> {
         self.entities.delete_component_by_entity_id::<T>(index)        
     }
@@ -101,12 +336,408 @@ impl World {
         self.entities.add_component_to_entity_by_id(data, index)
     }
 
+    /// Makes `child` a child of `parent`, moving it away from its previous parent.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// world.register_component::<u32>();
+    /// world.create_entity().with_component(0_u32).unwrap();
+    /// world.create_entity().with_component(1_u32).unwrap();
+    /// world.set_parent(1, 0).unwrap();
+    /// assert_eq!(world.parent(1), Some(0));
+    /// assert_eq!(world.children(0), vec![1]);
+    /// ```
+    pub fn set_parent(&mut self, child:usize, parent:usize) -> Result<()> {
+        self.entities.set_parent(child, parent)
+    }
+
+    pub fn add_child(&mut self, parent:usize, child:usize) -> Result<()> {
+        self.entities.add_child(parent, child)
+    }
+
+    pub fn remove_child(&mut self, parent:usize, child:usize) -> Result<()> {
+        self.entities.remove_child(parent, child)
+    }
+
+    pub fn parent(&self, index:usize) -> Option<usize> {
+        self.entities.parent(index)
+    }
+
+    pub fn children(&self, index:usize) -> Vec<usize> {
+        self.entities.children(index)
+    }
+
+    pub fn ancestors(&self, index:usize) -> Ancestors<'_> {
+        self.entities.ancestors(index)
+    }
+
+    pub fn descendants(&self, index:usize) -> Descendants<'_> {
+        self.entities.descendants(index)
+    }
+
+    pub fn depth_first(&self, index:usize) -> DepthFirst<'_> {
+        self.entities.depth_first(index)
+    }
+
+    /// Stores the pair `(R, target)` on `entity`. Relations are removed
+    /// automatically when the target gets deleted.
+    /// ```
+    /// use::ecs_library::World;
+    /// struct Likes;
+    /// let mut world = World::new();
+    /// world.register_component::<u32>();
+    /// world.create_entity().with_component(0_u32).unwrap();
+    /// world.create_entity().with_component(1_u32).unwrap();
+    /// world.add_relation::<Likes>(0, 1).unwrap();
+    /// assert_eq!(world.relation_targets::<Likes>(0), vec![1]);
+    /// assert_eq!(world.entities_with_relation::<Likes>(1), vec![0]);
+    /// ```
+    pub fn add_relation<R:Any>(&mut self, entity:usize, target:usize) -> Result<()> {
+        self.entities.add_relation::<R>(entity, target)
+    }
+
+    pub fn remove_relation<R:Any>(&mut self, entity:usize, target:usize) -> Result<()> {
+        self.entities.remove_relation::<R>(entity, target)
+    }
+
+    pub fn has_relation<R:Any>(&self, entity:usize, target:usize) -> bool {
+        self.entities.has_relation::<R>(entity, target)
+    }
+
+    pub fn relation_targets<R:Any>(&self, entity:usize) -> Vec<usize> {
+        self.entities.relation_targets::<R>(entity)
+    }
+
+    pub fn entities_with_relation<R:Any>(&self, target:usize) -> Vec<usize> {
+        self.entities.entities_with_relation::<R>(target)
+    }
+
+    /// Handle of the entity stored at `index`, for keeping a reference to it
+    /// that notices when the entity gets deleted.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// world.register_component::<u32>();
+    /// world.create_entity().with_component(10_u32).unwrap();
+    /// let entity = world.entity_handle(0).unwrap();
+    /// world.delete_entity(entity).unwrap();
+    /// assert!(!world.is_alive(entity));
+    /// assert!(world.delete_entity(entity).is_err());
+    /// ```
+    pub fn entity_handle(&self, index:usize) -> Option<Entity> {
+        self.entities.entity_handle(index)
+    }
+
+    pub fn is_alive(&self, entity:Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    /// Like `delete_entity_by_id` but refuses handles to entities that are already gone.
+    pub fn delete_entity(&mut self, entity:Entity) -> Result<()> {
+        if !self.entities.is_alive(entity) {
+            return Err(Error::EntityDoesNotExist(format!("{entity:?} is no longer alive")));
+        }
+        self.entities.delete_entity_by_id(entity.index())
+    }
+
+    /// Copies the cloneable components of the entity into a new one with the same parent.
+    pub fn clone_entity(&mut self, entity:Entity) -> Result<Entity> {
+        let index = self.alive_indexes(&[entity])?[0];
+        self.entities.clone_entity(index)
+    }
+
+    /// Captures the cloneable components of the entity and its children as a prefab.
+    pub fn prefab_of(&self, entity:Entity) -> Result<Prefab> {
+        let index = self.alive_indexes(&[entity])?[0];
+        self.entities.prefab_of(index)
+    }
+
+    pub fn spawn_prefab(&mut self, prefab:&Prefab) -> Result<Entity> {
+        let entity = self.entities.spawn_prefab(prefab)?;
+        self.sync_registry();
+        Ok(entity)
+    }
+
+    /// Spawns the prefab with the components of `overrides` replacing those of the root.
+    pub fn spawn_prefab_with(&mut self, prefab:&Prefab, overrides:&Prefab) -> Result<Entity> {
+        let entity = self.entities.spawn_prefab_with(prefab, overrides)?;
+        self.sync_registry();
+        Ok(entity)
+    }
+
+    /// Allows copying `T` to other worlds with `copy_entities_to`.
+    pub fn register_clone<T:Any + Clone>(&mut self) {
+        self.entities.register_clone::<T>();
+        self.registry.register::<T>();
+    }
+
+    /// Makes transfers between worlds point the references inside `T` at the new entities.
+    pub fn register_map_entities<T:Any + MapEntities>(&mut self) {
+        self.entities.register_map_entities::<T>();
+        self.registry.register::<T>();
+    }
+
+    /// Copies the entities and their descendants into `destination`, registering
+    /// component types it does not know yet. All components must have a clone function.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut simulation = World::new();
+    /// simulation.register_clone::<u32>();
+    /// simulation.create_entity().with_component(7_u32).unwrap();
+    /// let entity = simulation.entity_handle(0).unwrap();
+    ///
+    /// let mut prediction = World::new();
+    /// let map = simulation.copy_entities_to(&[entity], &mut prediction).unwrap();
+    /// let copy = map.get_entity(entity).unwrap();
+    /// assert_eq!(*prediction.get::<u32>(copy.index()).unwrap(), 7);
+    /// ```
+    pub fn copy_entities_to(&self, entities:&[Entity], destination:&mut World) -> Result<EntityMap> {
+        let roots = self.alive_indexes(entities)?;
+        let map = self.entities.copy_entities_to(&roots, &mut destination.entities)?;
+        destination.registry.merge(&self.registry);
+        destination.sync_registry();
+        Ok(map)
+    }
+
+    /// Moves the entities and their descendants into `destination` and deletes them here.
+    pub fn move_entities_to(&mut self, entities:&[Entity], destination:&mut World) -> Result<EntityMap> {
+        let roots = self.alive_indexes(entities)?;
+        let map = self.entities.move_entities_to(&roots, &mut destination.entities)?;
+        destination.registry.merge(&self.registry);
+        destination.sync_registry();
+        Ok(map)
+    }
+
+    /// Moves every entity of `other` into this world. Resources this world
+    /// does not have yet are taken over as well.
+    pub fn merge(&mut self, mut other: World) -> Result<EntityMap> {
+        let roots = other.entities
+            .iter_entities()
+            .filter(|entity| other.entities.parent(entity.index()).is_none())
+            .collect::<Vec<_>>();
+        let map = other.move_entities_to(&roots, self)?;
+        self.resources.merge(other.resources);
+        Ok(map)
+    }
+
+    fn alive_indexes(&self, entities:&[Entity]) -> Result<Vec<usize>> {
+        entities
+            .iter()
+            .map(|entity| {
+                if self.entities.is_alive(*entity) {
+                    Ok(entity.index())
+                } else {
+                    Err(Error::EntityDoesNotExist(format!("{entity:?} is no longer alive")))
+                }
+            })
+            .collect()
+    }
+
+    /// Deleting an entity also deletes all of its descendants.
     pub fn delete_entity_by_id(&mut self, index:usize) -> Result<()> {
         self.entities.delete_entity_by_id(index)?;
 
         Ok(())     
     }
 
+    /// Registers a component whose type is only known at runtime.
+    /// Its data is an untyped byte blob.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// let armor = world.register_dynamic_component("armor").unwrap();
+    /// world.create_entity().with_dynamic_component(armor, vec![3]).unwrap();
+    /// assert_eq!(*world.get_dynamic_component(armor, 0).unwrap(), vec![3]);
+    /// ```
+    pub fn register_dynamic_component(&mut self, name: &str) -> Result<ComponentId> {
+        self.entities.register_dynamic_component(name)
+    }
+
+    pub fn dynamic_component_id(&self, name: &str) -> Option<ComponentId> {
+        self.entities.dynamic_component_id(name)
+    }
+
+    pub fn get_dynamic_component(&self, id: ComponentId, index:usize) -> Option<Ref<'_, Vec<u8>>> {
+        self.entities.get_dynamic_component(id, index)
+    }
+
+    pub fn get_dynamic_component_mut(&mut self, id: ComponentId, index:usize) -> Option<RefMut<'_, Vec<u8>>> {
+        self.entities.get_dynamic_component_mut(id, index)
+    }
+
+    pub fn add_dynamic_component_to_entity_by_id(&mut self, id: ComponentId, data: Vec<u8>, index:usize) -> Result<()> {
+        self.entities.add_dynamic_component_to_entity_by_id(id, data, index)
+    }
+
+    pub fn delete_dynamic_component_by_entity_id(&mut self, id: ComponentId, index:usize) -> Result<()> {
+        self.entities.delete_dynamic_component_by_entity_id(id, index)
+    }
+
+    /// Registers a component that is stored in binary snapshots.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// world.register_binary_component::<u32>();
+    /// world.create_entity().with_component(10_u32).unwrap();
+    /// let snapshot = world.save_snapshot();
+    ///
+    /// let mut loaded = World::new();
+    /// loaded.register_binary_component::<u32>();
+    /// loaded.load_snapshot(&snapshot).unwrap();
+    /// let query = loaded.query().with_component::<u32>().unwrap().run();
+    /// assert_eq!(query.0, vec![0]);
+    /// ```
+    pub fn register_binary_component<T: BinaryComponent>(&mut self) {
+        self.register_component::<T>();
+        self.entities.register_codec::<T>();
+    }
+
+    /// Names, sizes and optional `Debug`/`Reflect` support of the registered components.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// world.register_component::<u32>();
+    /// world.type_registry_mut().register_debug::<u32>();
+    /// world.create_entity().with_component(10_u32).unwrap();
+    /// assert_eq!(world.debug_entity(0).unwrap(), vec![("u32", "10".to_string())]);
+    /// ```
+    pub fn type_registry(&self) -> &TypeRegistry {
+        &self.registry
+    }
+
+    pub fn type_registry_mut(&mut self) -> &mut TypeRegistry {
+        &mut self.registry
+    }
+
+    /// Adds the component types `entities` registered on its own, e.g. for
+    /// a prefab or a transfer, so `debug_entity` does not leave them out.
+    fn sync_registry(&mut self) {
+        for (type_id, name, size) in self.entities.registered_types() {
+            self.registry.register_untyped(type_id, name, size);
+        }
+        if self.entities.get_bitmask(&TypeId::of::<Name>()).is_some() {
+            self.registry.register_debug::<Name>();
+        }
+    }
+
+    /// Type name and formatted value of every component on the entity, sorted by name.
+    pub fn debug_entity(&self, index:usize) -> Result<Vec<(&'static str, String)>> {
+        let mut components = self.entities
+            .components_of(index)?
+            .into_iter()
+            .filter_map(|(type_id, component)| {
+                let info = self.registry.get(&type_id)?;
+                Some((info.name, info.format(&*component.borrow())))
+            })
+            .collect::<Vec<_>>();
+        components.sort();
+        Ok(components)
+    }
+
+    /// Names an entity so it can be found with `entity_by_name`.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// world.register_component::<u32>();
+    /// world.create_entity().with_component(1_u32).unwrap();
+    /// world.set_name(0, "door_01").unwrap();
+    /// assert_eq!(world.entity_by_name("door_01").unwrap().index(), 0);
+    /// ```
+    pub fn set_name(&mut self, index:usize, name: impl Into<String>) -> Result<()> {
+        self.entities.set_name(index, name)?;
+        self.sync_registry();
+        Ok(())
+    }
+
+    pub fn name(&self, index:usize) -> Option<Ref<'_, str>> {
+        self.entities.name(index)
+    }
+
+    pub fn entity_by_name(&self, name:&str) -> Option<Entity> {
+        self.entities.entity_by_name(name)
+    }
+
+    /// Gives generic field access to one component of an entity, picked by type name.
+    pub fn reflect_component_mut<R>(
+        &mut self,
+        index:usize,
+        type_name: &str,
+        f: impl FnOnce(&mut dyn Reflect) -> R,
+    ) -> Result<R> {
+        let info = *self.registry.get_by_name(type_name).ok_or_else(
+            || Error::ComponetNotRegister(format!("{type_name} is not registered"))
+        )?;
+        let (_type_id, component) = self.entities
+            .components_of(index)?
+            .into_iter()
+            .find(|(type_id, _component)| *type_id == info.type_id)
+            .ok_or_else(|| Error::ComponentNotFound(format!("entity {index} has no {type_name}")))?;
+        if self.entities.is_tag(&info.type_id) {
+            return Err(Error::TagNotMutable(format!("{type_name} is a tag and has no value to change")));
+        }
+        self.entities.component_mutated(&info.type_id, index);
+        let mut borrowed_component = component.borrow_mut();
+        let reflected = info.reflect_mut(&mut *borrowed_component).ok_or_else(
+            || Error::ComponentNotReflectable(format!("{type_name} was not registered for reflection"))
+        )?;
+        Ok(f(reflected))
+    }
+
+    /// Makes `snapshot` and `restore` include the resource `T`.
+    pub fn register_snapshot_resource<T: Any + Clone>(&mut self) {
+        self.snapshot_resources.insert(TypeId::of::<T>(), clone_resource::<T>);
+    }
+
+    /// In-memory copy of the world for rollback: every entity with its
+    /// components, generations and free slots, and the registered resources.
+    /// All components must have a clone function.
+    /// ```
+    /// use::ecs_library::World;
+    /// let mut world = World::new();
+    /// world.register_clone::<u32>();
+    /// world.register_snapshot_resource::<u64>();
+    /// world.add_resouce(1_u64);
+    /// world.create_entity().with_component(10_u32).unwrap();
+    /// let snapshot = world.snapshot().unwrap();
+    ///
+    /// *world.get_mut::<u32>(0).unwrap() = 11;
+    /// world.add_resouce(2_u64);
+    /// world.restore(&snapshot).unwrap();
+    /// assert_eq!(*world.get::<u32>(0).unwrap(), 10);
+    /// assert_eq!(*world.get_resource::<u64>().unwrap(), 1);
+    /// ```
+    pub fn snapshot(&self) -> Result<WorldSnapshot> {
+        let mut resources = self.snapshot_resources
+            .iter()
+            .filter_map(|(type_id, clone)| {
+                let data = self.resources.get_by_id(type_id)?;
+                Some((*type_id, Rc::<dyn Any>::from(clone(data))))
+            })
+            .collect::<Vec<_>>();
+        resources.sort_by_key(|(type_id, _data)| *type_id);
+        Ok(WorldSnapshot { entities: self.entities.snapshot()?, resources })
+    }
+
+    /// Rolls back to `snapshot`. Registered resources that did not exist then are removed.
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) -> Result<()> {
+        self.entities.restore(&snapshot.entities)?;
+        for (type_id, clone) in &self.snapshot_resources {
+            match snapshot.resources.iter().find(|(other, _data)| other == type_id) {
+                Some((_type_id, data)) => self.resources.insert_by_id(*type_id, clone(&**data)),
+                None => self.resources.remove_by_id(type_id),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save_snapshot(&self) -> Vec<u8> {
+        self.entities.save_snapshot()
+    }
+
+    pub fn load_snapshot(&mut self, bytes: &[u8]) -> Result<()> {
+        self.entities.load_snapshot(bytes)
+    }
+
 }
 
 
@@ -0,0 +1,186 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+
+/// Field level access to a component, so tools can read and edit it
+/// without knowing the concrete type.
+pub trait Reflect: Any {
+    fn field_names(&self) -> &'static [&'static str];
+    fn field(&self, name: &str) -> Option<&dyn Any>;
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Any>;
+}
+
+type DebugFn = fn(&dyn Any) -> Option<String>;
+type ReflectFn = fn(&dyn Any) -> Option<&dyn Reflect>;
+type ReflectMutFn = fn(&mut dyn Any) -> Option<&mut dyn Reflect>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TypeInfo {
+    pub type_id: TypeId,
+    pub name: &'static str,
+    pub size: usize,
+    debug: Option<DebugFn>,
+    reflect: Option<ReflectFn>,
+    reflect_mut: Option<ReflectMutFn>,
+}
+
+impl TypeInfo {
+    pub fn of<T: Any>() -> Self {
+        Self::untyped(TypeId::of::<T>(), std::any::type_name::<T>(), std::mem::size_of::<T>())
+    }
+
+    fn untyped(type_id: TypeId, name: &'static str, size: usize) -> Self {
+        Self { type_id, name, size, debug: None, reflect: None, reflect_mut: None }
+    }
+
+    /// Formats the value with `Debug` when it was registered,
+    /// otherwise falls back to the type name and size.
+    pub fn format(&self, data: &dyn Any) -> String {
+        self.debug
+            .and_then(|debug| debug(data))
+            .unwrap_or_else(|| format!("{} ({} bytes)", self.name, self.size))
+    }
+
+    pub fn is_reflectable(&self) -> bool {
+        self.reflect.is_some()
+    }
+
+    pub fn reflect<'a>(&self, data: &'a dyn Any) -> Option<&'a dyn Reflect> {
+        self.reflect.and_then(|reflect| reflect(data))
+    }
+
+    pub fn reflect_mut<'a>(&self, data: &'a mut dyn Any) -> Option<&'a mut dyn Reflect> {
+        self.reflect_mut.and_then(|reflect_mut| reflect_mut(data))
+    }
+}
+
+fn debug_component<T: Any + Debug>(data: &dyn Any) -> Option<String> {
+    data.downcast_ref::<T>().map(|data| format!("{data:?}"))
+}
+
+fn reflect_component<T: Reflect>(data: &dyn Any) -> Option<&dyn Reflect> {
+    data.downcast_ref::<T>().map(|data| data as &dyn Reflect)
+}
+
+fn reflect_component_mut<T: Reflect>(data: &mut dyn Any) -> Option<&mut dyn Reflect> {
+    data.downcast_mut::<T>().map(|data| data as &mut dyn Reflect)
+}
+
+#[derive(Default, Debug)]
+pub struct TypeRegistry {
+    types: HashMap<TypeId, TypeInfo>,
+}
+
+impl TypeRegistry {
+
+    pub fn register<T: Any>(&mut self) -> &mut TypeInfo {
+        self.types.entry(TypeId::of::<T>()).or_insert_with(TypeInfo::of::<T>)
+    }
+
+    /// For types that are only known by id, like the ones a prefab registers.
+    pub(crate) fn register_untyped(&mut self, type_id: TypeId, name: &'static str, size: usize) {
+        self.types.entry(type_id).or_insert_with(|| TypeInfo::untyped(type_id, name, size));
+    }
+
+    pub fn register_debug<T: Any + Debug>(&mut self) {
+        self.register::<T>().debug = Some(debug_component::<T>);
+    }
+
+    pub fn register_reflect<T: Reflect>(&mut self) {
+        let info = self.register::<T>();
+        info.reflect = Some(reflect_component::<T>);
+        info.reflect_mut = Some(reflect_component_mut::<T>);
+    }
+
+    pub fn get(&self, type_id: &TypeId) -> Option<&TypeInfo> {
+        self.types.get(type_id)
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&TypeInfo> {
+        self.types.values().find(|info| info.name == name)
+    }
+
+    /// Adds the types of `other` that are not registered here.
+    pub fn merge(&mut self, other: &TypeRegistry) {
+        for (type_id, info) in &other.types {
+            self.types.entry(*type_id).or_insert(*info);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TypeInfo> {
+        self.types.values()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Location {
+        x: f32,
+        y: f32,
+    }
+
+    impl Reflect for Location {
+        fn field_names(&self) -> &'static [&'static str] {
+            &["x", "y"]
+        }
+
+        fn field(&self, name: &str) -> Option<&dyn Any> {
+            match name {
+                "x" => Some(&self.x),
+                "y" => Some(&self.y),
+                _ => None,
+            }
+        }
+
+        fn field_mut(&mut self, name: &str) -> Option<&mut dyn Any> {
+            match name {
+                "x" => Some(&mut self.x),
+                "y" => Some(&mut self.y),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn register_records_name_and_size() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<u32>();
+
+        let info = registry.get(&TypeId::of::<u32>()).unwrap();
+        assert_eq!(info.name, "u32");
+        assert_eq!(info.size, 4);
+        assert!(!info.is_reflectable());
+    }
+
+    #[test]
+    fn format_uses_debug_when_registered() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<u32>();
+        let info = *registry.get(&TypeId::of::<u32>()).unwrap();
+        assert_eq!(info.format(&10_u32), "u32 (4 bytes)");
+
+        registry.register_debug::<u32>();
+        let info = registry.get(&TypeId::of::<u32>()).unwrap();
+        assert_eq!(info.format(&10_u32), "10");
+    }
+
+    #[test]
+    fn reflect_edits_fields() {
+        let mut registry = TypeRegistry::default();
+        registry.register_reflect::<Location>();
+        let info = *registry.get_by_name(std::any::type_name::<Location>()).unwrap();
+
+        let mut location = Location { x: 1.0, y: 2.0 };
+        let reflected = info.reflect_mut(&mut location).unwrap();
+        *reflected.field_mut("y").unwrap().downcast_mut::<f32>().unwrap() = 5.0;
+
+        let reflected = info.reflect(&location).unwrap();
+        assert_eq!(reflected.field_names(), &["x", "y"]);
+        assert_eq!(*reflected.field("x").unwrap().downcast_ref::<f32>().unwrap(), 1.0);
+        assert_eq!(location.y, 5.0);
+    }
+}
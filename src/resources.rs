@@ -41,6 +41,25 @@ impl Resource {
         self.data.remove(&type_id);
     }
 
+    pub(crate) fn get_by_id(&self, type_id: &TypeId) -> Option<&dyn Any> {
+        self.data.get(type_id).map(|data| &**data)
+    }
+
+    pub(crate) fn insert_by_id(&mut self, type_id: TypeId, data: Box<dyn Any>) {
+        self.data.insert(type_id, data);
+    }
+
+    pub(crate) fn remove_by_id(&mut self, type_id: &TypeId) {
+        self.data.remove(type_id);
+    }
+
+    /// Takes over the resources of `other` that are not present here.
+    pub fn merge(&mut self, other: Resource) {
+        for (type_id, data) in other.data {
+            self.data.entry(type_id).or_insert(data);
+        }
+    }
+
 }
 
 #[cfg(test)] 
@@ -0,0 +1,287 @@
+//! Scene files. A scene is plain text listing entities by a scene-local id,
+//! their components and their parent:
+//!
+//! ```text
+//! # the front door
+//! entity door_01
+//!     Position: 4.0 2.0
+//!     Health: 10
+//!
+//! entity hinge
+//!     parent: door_01
+//!     Position: 0.0 1.0
+//! ```
+//!
+//! Component names map to deserializers registered on a `SceneLoader`, which
+//! turn the text after the colon into a component. A parent has to be
+//! listed before its children, and every entity needs at least one component
+//! since an entity without any is not alive.
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use crate::entity::Component;
+use crate::{Entity, Error, Name, Result, World};
+
+
+type Deserializer = Rc<dyn Fn(&str) -> Option<Component>>;
+
+#[derive(Clone)]
+struct SceneType {
+    type_id: TypeId,
+    deserialize: Deserializer,
+    register: fn(&mut World),
+}
+
+fn register_scene_component<T: Any>(world: &mut World) {
+    world.register_component::<T>();
+}
+
+/// Knows which component names a scene may use and how to read their values.
+#[derive(Clone)]
+pub struct SceneLoader {
+    types: HashMap<String, SceneType>,
+}
+
+impl Default for SceneLoader {
+    fn default() -> Self {
+        let mut loader = Self { types: HashMap::new() };
+        loader.register_with("Name", |text| Some(Name::new(text)));
+        loader
+    }
+}
+
+impl fmt::Debug for SceneLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.types.keys()).finish()
+    }
+}
+
+impl SceneLoader {
+    /// A loader that only knows `Name`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads `T` with its `FromStr` implementation.
+    pub fn register<T: Any + FromStr>(&mut self, name: &str) -> &mut Self {
+        self.register_with(name, |text| text.parse::<T>().ok())
+    }
+
+    pub fn register_with<T: Any>(&mut self, name: &str, deserialize: impl Fn(&str) -> Option<T> + 'static) -> &mut Self {
+        let scene_type = SceneType {
+            type_id: TypeId::of::<T>(),
+            deserialize: Rc::new(move |text| {
+                deserialize(text).map(|value| Rc::new(RefCell::new(value)) as Component)
+            }),
+            register: register_scene_component::<T>,
+        };
+        self.types.insert(name.to_string(), scene_type);
+        self
+    }
+
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Scene> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| Error::InvalidScene(format!("cannot read {}: {error}", path.display())))?;
+        self.parse(&text)
+    }
+
+    /// Checks the whole scene, including every component value, before returning it.
+    pub fn parse(&self, text: &str) -> Result<Scene> {
+        let mut entities: Vec<SceneEntity> = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let invalid = |message: String| Error::InvalidScene(format!("line {}: {message}", number + 1));
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(id) = line.strip_prefix("entity ") {
+                let id = id.trim();
+                if entities.iter().any(|entity| entity.id == id) {
+                    return Err(invalid(format!("entity {id} is listed twice")));
+                }
+                entities.push(SceneEntity { id: id.to_string(), parent: None, components: vec![] });
+                continue;
+            }
+
+            let known_ids = entities.iter().map(|entity| entity.id.clone()).collect::<Vec<_>>();
+            let entity = entities.last_mut().ok_or_else(|| invalid("expected `entity <id>`".to_string()))?;
+            let (key, value) = line
+                .split_once(':')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| invalid(format!("expected `<component>: <value>`, found `{line}`")))?;
+            if key == "parent" {
+                if !known_ids[..known_ids.len() - 1].iter().any(|id| id == value) {
+                    return Err(invalid(format!("parent {value} has to be listed before {}", entity.id)));
+                }
+                entity.parent = Some(value.to_string());
+                continue;
+            }
+            let scene_type = self.types
+                .get(key)
+                .ok_or_else(|| invalid(format!("no deserializer registered for {key}")))?;
+            if (scene_type.deserialize)(value).is_none() {
+                return Err(invalid(format!("`{value}` is not a valid {key}")));
+            }
+            if entity.components.iter().any(|(other, _value)| other.type_id == scene_type.type_id) {
+                return Err(invalid(format!("{key} is given twice for {}", entity.id)));
+            }
+            entity.components.push((scene_type.clone(), value.to_string()));
+        }
+
+        if let Some(entity) = entities.iter().find(|entity| entity.components.is_empty()) {
+            return Err(Error::InvalidScene(format!("entity {} has no components", entity.id)));
+        }
+        Ok(Scene { entities })
+    }
+}
+
+struct SceneEntity {
+    id: String,
+    parent: Option<String>,
+    components: Vec<(SceneType, String)>,
+}
+
+/// A parsed scene, ready to be spawned any number of times.
+pub struct Scene {
+    entities: Vec<SceneEntity>,
+}
+
+impl fmt::Debug for Scene {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.entities.iter().map(|entity| &entity.id)).finish()
+    }
+}
+
+impl Scene {
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+/// The entities one spawn of a scene created, by scene-local id.
+#[derive(Debug, Clone, Default)]
+pub struct LoadedScene {
+    entities: Vec<(String, Entity)>,
+}
+
+impl LoadedScene {
+    pub fn get(&self, id: &str) -> Option<Entity> {
+        self.entities
+            .iter()
+            .find(|(other, _entity)| other == id)
+            .map(|(_id, entity)| *entity)
+    }
+
+    /// Scene-local ids and their entities, in the order the scene lists them.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Entity)> {
+        self.entities.iter().map(|(id, entity)| (id.as_str(), *entity))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+impl World {
+    /// Creates the entities of the scene, registering component types as needed.
+    pub fn spawn_scene(&mut self, scene: &Scene) -> Result<LoadedScene> {
+        let mut loaded = LoadedScene::default();
+        for entity in &scene.entities {
+            if let Err(error) = self.spawn_scene_entity(entity, &mut loaded) {
+                if let Some(partial) = self.entities.current_entity() {
+                    self.delete_entity(partial)?;
+                }
+                self.despawn_scene(&loaded)?;
+                return Err(error);
+            }
+        }
+        Ok(loaded)
+    }
+
+    fn spawn_scene_entity(&mut self, entity: &SceneEntity, loaded: &mut LoadedScene) -> Result<()> {
+        let parent = entity.parent.as_deref().and_then(|parent| loaded.get(parent));
+        self.create_entity();
+        let index = self.entities.current_index();
+        for (scene_type, value) in &entity.components {
+            (scene_type.register)(self);
+            let component = (scene_type.deserialize)(value).ok_or_else(
+                || Error::InvalidScene(format!("`{value}` is not a valid component value"))
+            )?;
+            self.entities.insert_component(scene_type.type_id, index, component)?;
+        }
+        if let Some(parent) = parent {
+            self.add_child(parent.index(), index)?;
+        }
+        loaded.entities.push((entity.id.clone(), self.entity_handle(index).unwrap()));
+        Ok(())
+    }
+
+    /// Deletes whatever is left of a spawned scene. Returns how many of its entities were deleted.
+    pub fn despawn_scene(&mut self, loaded: &LoadedScene) -> Result<usize> {
+        let mut deleted = 0;
+        for (_id, entity) in loaded.iter() {
+            if self.is_alive(entity) {
+                deleted += self.entities.depth_first(entity.index()).count();
+                self.delete_entity(entity)?;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    fn loader() -> SceneLoader {
+        let mut loader = SceneLoader::new();
+        loader
+            .register::<u32>("Armor")
+            .register_with("Health", |text| text.parse().ok().map(Health));
+        loader
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let error = loader().parse("entity a\n  Health: lots\n").unwrap_err();
+        assert_eq!(format!("{error}"), "InvalidScene(\"line 2: `lots` is not a valid Health\")");
+
+        assert!(loader().parse("Health: 1").is_err());
+        assert!(loader().parse("entity a\n  Speed: 1").is_err());
+        assert!(loader().parse("entity a\n  parent: b\nentity b\n  Armor: 1").is_err());
+        assert!(loader().parse("entity a\n  Armor: 1\nentity a\n  Armor: 2").is_err());
+        assert!(loader().parse("entity a\n").is_err());
+    }
+
+    #[test]
+    fn entities_with_only_a_parent_are_rejected() {
+        let error = loader().parse("entity a\n  Armor: 1\nentity b\n  parent: a\n").unwrap_err();
+        assert_eq!(format!("{error}"), "InvalidScene(\"entity b has no components\")");
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() -> Result<()> {
+        let scene = loader().parse("# a scene\n\nentity a\n  Health: 3\n\n  Armor: 2\n")?;
+        assert_eq!(scene.len(), 1);
+        Ok(())
+    }
+}
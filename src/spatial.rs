@@ -0,0 +1,169 @@
+//! Optional spatial hash. A `SpatialGrid<P>` buckets every entity that has the
+//! position component `P` into square cells and answers radius and box queries.
+//! `update` only moves the entities whose `P` changed since the previous call.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::transform::{GlobalTransform, Transform};
+use crate::{Entity, Error, Result, World};
+
+
+/// A component the grid can read a 2D position from.
+pub trait Position {
+    fn position(&self) -> (f32, f32);
+}
+
+impl Position for Transform {
+    fn position(&self) -> (f32, f32) {
+        self.translation
+    }
+}
+
+impl Position for GlobalTransform {
+    fn position(&self) -> (f32, f32) {
+        self.get().translation
+    }
+}
+
+type Cell = (i32, i32);
+
+#[derive(Debug)]
+pub struct SpatialGrid<P> {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<usize>>,
+    entries: HashMap<usize, (Entity, (f32, f32))>,
+    last_tick: u64,
+    position: PhantomData<P>,
+}
+
+impl<P: Position + Any> SpatialGrid<P> {
+    /// `cell_size` is best close to the radius most queries use.
+    /// Fails unless it is positive and finite.
+    pub fn new(cell_size: f32) -> Result<Self> {
+        if !(cell_size > 0.0 && cell_size.is_finite()) {
+            return Err(Error::InvalidCellSize(format!("cell size must be positive, got {cell_size}")));
+        }
+        Ok(Self {
+            cell_size,
+            cells: HashMap::new(),
+            entries: HashMap::new(),
+            last_tick: 0,
+            position: PhantomData,
+        })
+    }
+
+    /// Picks up entities whose `P` was added, changed or removed since the last update.
+    /// Returns how many entities were looked at.
+    pub fn update(&mut self, world: &World) -> usize {
+        let changed = world.changed_since::<P>(self.last_tick);
+        self.last_tick = world.change_tick();
+        for index in &changed {
+            self.remove(*index);
+            let entity = world.entity_handle(*index);
+            let position = world.get::<P>(*index).map(|position| position.position());
+            if let (Some(entity), Some(position)) = (entity, position) {
+                self.insert(*index, entity, position);
+            }
+        }
+        changed.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entities at most `radius` away from `center`, by index.
+    pub fn within_radius(&self, center: (f32, f32), radius: f32) -> Vec<Entity> {
+        let min = (center.0 - radius, center.1 - radius);
+        let max = (center.0 + radius, center.1 + radius);
+        self.collect(min, max, |(x, y)| {
+            let (dx, dy) = (x - center.0, y - center.1);
+            dx * dx + dy * dy <= radius * radius
+        })
+    }
+
+    /// Entities inside the box from `min` to `max`, edges included, by index.
+    pub fn within_aabb(&self, min: (f32, f32), max: (f32, f32)) -> Vec<Entity> {
+        self.collect(min, max, |(x, y)| x >= min.0 && x <= max.0 && y >= min.1 && y <= max.1)
+    }
+
+    fn cell(&self, (x, y): (f32, f32)) -> Cell {
+        ((x / self.cell_size).floor() as i32, (y / self.cell_size).floor() as i32)
+    }
+
+    fn insert(&mut self, index: usize, entity: Entity, position: (f32, f32)) {
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push(index);
+        self.entries.insert(index, (entity, position));
+    }
+
+    fn remove(&mut self, index: usize) {
+        let Some((_entity, position)) = self.entries.remove(&index) else {
+            return;
+        };
+        let cell = self.cell(position);
+        let indexes = self.cells.get_mut(&cell).unwrap();
+        indexes.retain(|other| *other != index);
+        if indexes.is_empty() {
+            self.cells.remove(&cell);
+        }
+    }
+
+    /// Visits the cells overlapping the box, or every occupied cell when that is fewer.
+    fn collect(&self, min: (f32, f32), max: (f32, f32), accept: impl Fn((f32, f32)) -> bool) -> Vec<Entity> {
+        let (low, high) = (self.cell(min), self.cell(max));
+        let covered = (high.0 as i64 - low.0 as i64 + 1).saturating_mul(high.1 as i64 - low.1 as i64 + 1);
+        let in_range = |cell: &Cell| cell.0 >= low.0 && cell.0 <= high.0 && cell.1 >= low.1 && cell.1 <= high.1;
+
+        let candidates: Vec<usize> = if covered > self.cells.len() as i64 {
+            self.cells
+                .iter()
+                .filter(|(cell, _indexes)| in_range(cell))
+                .flat_map(|(_cell, indexes)| indexes.iter().copied())
+                .collect()
+        } else {
+            (low.0..=high.0)
+                .flat_map(|x| (low.1..=high.1).map(move |y| (x, y)))
+                .filter_map(|cell| self.cells.get(&cell))
+                .flat_map(|indexes| indexes.iter().copied())
+                .collect()
+        };
+
+        let mut found = candidates
+            .into_iter()
+            .map(|index| self.entries[&index])
+            .filter(|(_entity, position)| accept(*position))
+            .map(|(entity, _position)| entity)
+            .collect::<Vec<_>>();
+        found.sort();
+        found
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn negative_positions_get_their_own_cells() -> Result<()> {
+        let grid = SpatialGrid::<Transform>::new(10.0)?;
+
+        assert_eq!(grid.cell((5.0, 5.0)), (0, 0));
+        assert_eq!(grid.cell((-5.0, 5.0)), (-1, 0));
+        assert_eq!(grid.cell((-10.0, -10.5)), (-1, -2));
+        Ok(())
+    }
+
+    #[test]
+    fn cell_size_must_be_positive() {
+        assert!(matches!(SpatialGrid::<Transform>::new(0.0), Err(Error::InvalidCellSize(_))));
+        assert!(matches!(SpatialGrid::<Transform>::new(f32::NAN), Err(Error::InvalidCellSize(_))));
+    }
+}
@@ -0,0 +1,198 @@
+//! Optional 2D transforms. Entities with a `Transform` and a `GlobalTransform`
+//! get their world space transform computed by `propagate_transforms`,
+//! following `TransformParent` links.
+
+use std::collections::HashMap;
+
+use crate::entity::Component;
+use crate::{EntityMap, Error, MapEntities, Result, World};
+
+
+/// Position, rotation (radians) and uniform scale relative to the parent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: (f32, f32),
+    pub rotation: f32,
+    pub scale: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self { translation: (0.0, 0.0), rotation: 0.0, scale: 1.0 };
+
+    pub fn from_translation(x: f32, y: f32) -> Self {
+        Self { translation: (x, y), ..Self::IDENTITY }
+    }
+
+    pub fn transform_point(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (sin, cos) = self.rotation.sin_cos();
+        let (x, y) = (x * self.scale, y * self.scale);
+        (
+            self.translation.0 + x * cos - y * sin,
+            self.translation.1 + x * sin + y * cos,
+        )
+    }
+
+    /// `child` expressed in the space `self` is expressed in.
+    pub fn mul_transform(&self, child: &Transform) -> Transform {
+        Transform {
+            translation: self.transform_point(child.translation),
+            rotation: self.rotation + child.rotation,
+            scale: self.scale * child.scale,
+        }
+    }
+}
+
+/// World space transform, written by `propagate_transforms`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GlobalTransform {
+    transform: Transform,
+    computed_from: Option<(Transform, Option<usize>)>,
+}
+
+impl GlobalTransform {
+    pub fn get(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+/// Makes the entity's `Transform` relative to the global transform of the target entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransformParent(pub usize);
+
+/// Links to entities that were not transferred along are dropped, since the
+/// index would point at an unrelated entity in the destination.
+impl MapEntities for TransformParent {
+    fn map_entities(&mut self, map: &EntityMap) -> bool {
+        match map.get(self.0) {
+            Some(parent) => {
+                self.0 = parent;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub fn register_transform_components(world: &mut World) {
+    world.register_clone::<Transform>();
+    world.register_clone::<GlobalTransform>();
+    world.register_clone::<TransformParent>();
+    world.register_map_entities::<TransformParent>();
+}
+
+/// Recomputes `GlobalTransform` for every entity whose local transform or parent
+/// link changed since the last pass, together with everything below it.
+/// Written global transforms are marked changed.
+/// Returns how many global transforms were written, or `InvalidHierarchy`
+/// without writing any when the parent links form a cycle.
+pub fn propagate_transforms(world: &World) -> Result<usize> {
+    let (indexes, components) = world.query()
+        .with_component::<Transform>()?
+        .with_component::<GlobalTransform>()?
+        .run();
+    let nodes = indexes
+        .iter()
+        .enumerate()
+        .map(|(row, index)| (*index, (&components[0][row], &components[1][row])))
+        .collect::<HashMap<usize, (&Component, &Component)>>();
+
+    let (linked, links) = world.query()
+        .with_component::<TransformParent>()?
+        .run();
+    let mut parents = HashMap::new();
+    let mut children = HashMap::<usize, Vec<usize>>::new();
+    for (row, child) in linked.iter().enumerate() {
+        let borrowed_link = links[0][row].borrow();
+        let parent = borrowed_link.downcast_ref::<TransformParent>().unwrap().0;
+        if nodes.contains_key(&parent) && nodes.contains_key(child) {
+            parents.insert(*child, parent);
+            children.entry(parent).or_default().push(*child);
+        }
+    }
+
+    check_for_cycles(&indexes, &parents, &children)?;
+
+    let mut updated = 0;
+    let mut stack = indexes
+        .iter()
+        .filter(|index| !parents.contains_key(index))
+        .map(|index| (*index, Transform::IDENTITY, false))
+        .collect::<Vec<_>>();
+    while let Some((index, parent_global, parent_changed)) = stack.pop() {
+        let (local, global) = nodes[&index];
+        let local = *local.borrow().downcast_ref::<Transform>().unwrap();
+        let mut borrowed_global = global.borrow_mut();
+        let global = borrowed_global.downcast_mut::<GlobalTransform>().unwrap();
+
+        let source = Some((local, parents.get(&index).copied()));
+        let changed = parent_changed || global.computed_from != source;
+        if changed {
+            global.transform = parent_global.mul_transform(&local);
+            global.computed_from = source;
+            world.mark_changed::<GlobalTransform>(index);
+            updated += 1;
+        }
+        for child in children.get(&index).into_iter().flatten() {
+            stack.push((*child, global.transform, changed));
+        }
+    }
+
+    Ok(updated)
+}
+
+/// Every node has to be reachable from a node without a parent,
+/// the ones that are not sit on or below a cycle.
+fn check_for_cycles(
+    indexes: &[usize],
+    parents: &HashMap<usize, usize>,
+    children: &HashMap<usize, Vec<usize>>,
+) -> Result<()> {
+    let mut stack = indexes
+        .iter()
+        .copied()
+        .filter(|index| !parents.contains_key(index))
+        .collect::<Vec<_>>();
+    let mut reached = 0;
+    while let Some(index) = stack.pop() {
+        reached += 1;
+        stack.extend(children.get(&index).into_iter().flatten());
+    }
+    if reached != indexes.len() {
+        return Err(Error::InvalidHierarchy("transform parents form a cycle".to_string()));
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(actual: (f32, f32), expected: (f32, f32)) {
+        assert!((actual.0 - expected.0).abs() < 1e-5, "{actual:?} != {expected:?}");
+        assert!((actual.1 - expected.1).abs() < 1e-5, "{actual:?} != {expected:?}");
+    }
+
+    #[test]
+    fn mul_transform_applies_parent_rotation_and_scale() {
+        let parent = Transform { translation: (1.0, 0.0), rotation: std::f32::consts::FRAC_PI_2, scale: 2.0 };
+        let child = Transform::from_translation(1.0, 0.0);
+
+        let global = parent.mul_transform(&child);
+
+        assert_close(global.translation, (1.0, 2.0));
+        assert_eq!(global.scale, 2.0);
+    }
+
+    #[test]
+    fn identity_is_neutral() {
+        let transform = Transform { translation: (3.0, 4.0), rotation: 0.5, scale: 3.0 };
+        assert_eq!(Transform::IDENTITY.mul_transform(&transform), transform);
+    }
+}
@@ -0,0 +1,36 @@
+use ecs_library::asset::{AssetEvent, Handle};
+use ecs_library::{Prefab, World};
+
+
+
+#[test]
+fn deleting_the_last_holder_unloads_the_asset() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.add_assets::<Mesh>();
+    let mesh = world.assets_mut::<Mesh>().unwrap().add(Mesh(vec![0.0; 1024]));
+
+    let tree = Prefab::new().with(mesh.clone());
+    let first = world.spawn_prefab(&tree)?;
+    let second = world.spawn_prefab(&tree)?;
+    drop(tree);
+    let id = mesh.id();
+    drop(mesh);
+
+    assert_eq!(world.entities_with_asset::<Mesh>(id), vec![first, second]);
+    {
+        let handle = world.get::<Handle<Mesh>>(first.index()).unwrap();
+        assert_eq!(world.assets::<Mesh>().unwrap().get(&handle).unwrap().0.len(), 1024);
+    }
+
+    world.delete_entity(first)?;
+    assert_eq!(world.assets_mut::<Mesh>().unwrap().update(), 0);
+    world.delete_component_by_entity_id::<Handle<Mesh>>(second.index())?;
+
+    let events = world.assets_mut::<Mesh>().unwrap().drain_events();
+    assert_eq!(events, vec![AssetEvent::Added(id), AssetEvent::Unloaded(id)]);
+    assert!(world.assets::<Mesh>().unwrap().is_empty());
+
+    Ok(())
+}
+
+struct Mesh(Vec<f32>);
@@ -0,0 +1,95 @@
+use ecs_library::{BinaryComponent, Error, World};
+use std::any::TypeId;
+
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Score(u32);
+
+impl BinaryComponent for Score {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        u32::decode(bytes).map(Score)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Label(String);
+
+fn initialize_world() -> anyhow::Result<World> {
+    let mut world = World::new();
+    world.register_binary_component::<u32>();
+    world.register_binary_component::<f32>();
+    world.register_diff::<Label>();
+    world.register_diff_resource::<Score>();
+    world.add_resouce(Score(0));
+    world.create_entity().with_component(1_u32)?.with_component(1.0_f32)?;
+    world.create_entity().with_component(2_u32)?;
+    world.create_entity().with_component(3_u32)?.with_component(3.0_f32)?;
+    Ok(world)
+}
+
+#[test]
+fn encoded_deltas_bring_clients_up_to_date() -> anyhow::Result<()> {
+    let mut server = initialize_world()?;
+    let mut client = initialize_world()?;
+    server.delete_entity_by_id(1)?;
+    *server.get_mut::<u32>(0).unwrap() = 10;
+    server.delete_component_by_entity_id::<f32>(2)?;
+    server.create_entity().with_component(4_u32)?;
+    server.create_entity().with_component(5_u32)?.with_component(5.0_f32)?;
+    server.add_resouce(Score(7));
+
+    let delta = client.diff(&server)?;
+    assert_eq!(delta.entities().spawned().len(), 2);
+    assert_eq!(delta.entities().despawned(), &[client.entity_handle(1).unwrap()]);
+    assert_eq!(delta.entities().changed(), vec![(server.entity_handle(0).unwrap(), TypeId::of::<u32>())]);
+    assert_eq!(delta.changed_resources(), vec![TypeId::of::<Score>()]);
+
+    let bytes = server.encode_delta(&delta)?;
+    client.apply(&client.decode_delta(&bytes)?)?;
+
+    assert_eq!(client.save_snapshot(), server.save_snapshot());
+    assert_eq!(client.entity_handle(3), server.entity_handle(3));
+    assert_eq!(client.get_resource::<Score>().unwrap().0, 7);
+    assert!(client.diff(&server)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn reverse_deltas_undo_changes() -> anyhow::Result<()> {
+    let mut world = initialize_world()?;
+    let before = initialize_world()?;
+    world.add_component_to_entity_by_id(Label("door".to_string()), 1)?;
+    world.delete_entity_by_id(2)?;
+    world.delete_resource::<Score>();
+
+    let undo = world.diff(&before)?;
+    world.apply(&undo)?;
+
+    assert!(world.get::<Label>(1).is_none());
+    assert_eq!(*world.get::<f32>(2).unwrap(), 3.0);
+    assert_eq!(world.get_resource::<Score>().unwrap().0, 0);
+    assert!(world.diff(&before)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn only_components_with_codecs_can_be_encoded() -> anyhow::Result<()> {
+    let world = initialize_world()?;
+    let mut labelled = initialize_world()?;
+    labelled.add_component_to_entity_by_id(Label("door".to_string()), 0)?;
+
+    let delta = world.diff(&labelled)?;
+    assert!(matches!(world.encode_delta(&delta), Err(Error::UnknownSnapshotComponent(_))));
+
+    let bytes = world.encode_delta(&World::new().diff(&World::new())?)?;
+    assert!(matches!(world.decode_delta(&bytes[..bytes.len() - 1]), Err(Error::InvalidSnapshot(_))));
+
+    Ok(())
+}
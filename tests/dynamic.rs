@@ -0,0 +1,60 @@
+use ecs_library::World;
+
+
+
+#[test]
+fn query_mixes_static_and_dynamic_components() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    let armor = world.register_dynamic_component("armor")?;
+    let poison = world.register_dynamic_component("poison")?;
+
+    world.create_entity()
+        .with_component(Location(10.0, 11.0))?
+        .with_dynamic_component(armor, vec![5])?;
+    world.create_entity()
+        .with_dynamic_component(armor, vec![6])?
+        .with_dynamic_component(poison, vec![])?;
+    world.create_entity()
+        .with_component(Location(30.0, 31.0))?;
+
+    let query = world.query()
+        .with_component::<Location>()?
+        .with_dynamic_component(armor)?
+        .run();
+
+    assert_eq!(query.0, vec![0]);
+    let borrowed_location = query.1[0][0].borrow();
+    let location = borrowed_location.downcast_ref::<Location>().unwrap();
+    assert_eq!(location.0, 10.0);
+    assert_eq!(location.1, 11.0);
+    let borrowed_armor = query.1[1][0].borrow();
+    assert_eq!(*borrowed_armor.downcast_ref::<Vec<u8>>().unwrap(), vec![5]);
+
+    Ok(())
+}
+
+#[test]
+fn add_and_delete_dynamic_component_by_id() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    let armor = world.register_dynamic_component("armor")?;
+    world.create_entity()
+        .with_component(Location(10.0, 11.0))?;
+
+    world.add_dynamic_component_to_entity_by_id(armor, vec![1, 2], 0)?;
+    world.get_dynamic_component_mut(armor, 0).unwrap()[0] = 9;
+    assert_eq!(*world.get_dynamic_component(armor, 0).unwrap(), vec![9, 2]);
+    assert_eq!(world.dynamic_component_id("armor"), Some(armor));
+
+    world.delete_dynamic_component_by_entity_id(armor, 0)?;
+    let query = world.query()
+        .with_dynamic_component(armor)?
+        .run();
+    assert!(query.0.is_empty());
+    assert!(world.get_dynamic_component(armor, 0).is_none());
+
+    Ok(())
+}
+
+struct Location(pub f32, pub f32);
@@ -2,7 +2,7 @@
 // use std::cell::RefCell;
 // use std::rc::Rc;
 
-use ecs_library::World;
+use ecs_library::{Entity, EntityReference, World};
 
 
 
@@ -167,4 +167,223 @@ fn deleting_an_entity() -> anyhow::Result<()> {
 }
 
 struct Location(pub f32, pub f32);
-struct Size(pub f32);
\ No newline at end of file
+struct Size(pub f32);
+
+#[test]
+fn query_with_tag_components() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Player>();
+    world.register_component::<Enemy>();
+
+    world.create_entity()
+        .with_component(Location(10.0, 11.0))?
+        .with_component(Player)?;
+    world.create_entity()
+        .with_component(Location(20.0, 21.0))?
+        .with_component(Enemy)?;
+    world.create_entity()
+        .with_component(Location(30.0, 31.0))?
+        .with_component(Enemy)?;
+
+    world.delete_component_by_entity_id::<Enemy>(1)?;
+    world.add_component_to_entity_by_id(Player, 2)?;
+
+    let query = world.query()
+        .with_component::<Location>()?
+        .with_component::<Player>()?
+        .run();
+    assert_eq!(query.0, vec![0, 2]);
+    assert!(query.1[1][0].borrow().downcast_ref::<Player>().is_some());
+
+    let query = world.query()
+        .with_component::<Enemy>()?
+        .run();
+    assert_eq!(query.0, vec![2]);
+
+    Ok(())
+}
+
+struct Player;
+struct Enemy;
+
+
+#[test]
+fn access_components_of_one_entity() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+
+    world.create_entity()
+        .with_component(Location(10.0, 11.0))?;
+    world.create_entity()
+        .with_component(Location(20.0, 21.0))?
+        .with_component(Size(5.0))?;
+
+    world.get_mut::<Location>(1).unwrap().0 += 1.0;
+    assert_eq!(world.get::<Location>(1).unwrap().0, 21.0);
+    assert!(!world.has::<Size>(0));
+
+    {
+        let mut locations = world.get_many_mut::<Location>(&[0, 1])?;
+        locations[0].1 = 0.0;
+        locations[1].1 = 0.0;
+    }
+    assert_eq!(world.get::<Location>(0).unwrap().1, 0.0);
+
+    let entity = world.entity(1)?;
+    assert_eq!(entity.type_ids().len(), 2);
+    assert_eq!(entity.get::<Size>().unwrap().0, 5.0);
+
+    world.entity_mut(0)?.insert(Size(1.0))?;
+    assert!(world.entity(0)?.contains::<Size>());
+
+    Ok(())
+}
+
+
+#[test]
+fn query_helpers() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+
+    world.create_entity()
+        .with_component(Location(10.0, 11.0))?;
+    world.create_entity()
+        .with_component(Location(20.0, 21.0))?
+        .with_component(Size(5.0))?;
+
+    let mut query = world.query();
+    query.with_component::<Location>()?
+        .with_component::<Size>()?;
+    assert_eq!(query.count(), 1);
+    let (index, components) = query.single()?;
+    assert_eq!(index, 1);
+    assert_eq!(components[1].borrow().downcast_ref::<Size>().unwrap().0, 5.0);
+    assert!(query.get(0).is_none());
+
+    world.delete_entity_by_id(1)?;
+    let mut query = world.query();
+    query.with_component::<Size>()?;
+    assert!(query.is_empty());
+    assert!(query.single().is_err());
+
+    Ok(())
+}
+
+
+#[test]
+fn parallel_query_iteration() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+    for index in 0..500 {
+        world.create_entity()
+            .with_component(Location(index as f32, 0.0))?
+            .with_component(Size(2.0))?;
+    }
+
+    world.query()
+        .with_component::<Location>()?
+        .with_component::<Size>()?
+        .par_for_each_mut_with::<Location, Size>(32, |_index, location, size| {
+            location.1 = location.0 * size.0;
+        })?;
+
+    assert_eq!(world.get::<Location>(250).unwrap().1, 500.0);
+    Ok(())
+}
+
+
+#[test]
+fn query_items_carry_entity_handles() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+
+    world.create_entity()
+        .with_component(Location(10.0, 11.0))?
+        .with_component(Size(1.0))?;
+    world.create_entity()
+        .with_component(Location(20.0, 21.0))?
+        .with_component(Size(50.0))?;
+
+    let items = world.query()
+        .with_component::<Size>()?
+        .run_entities();
+    let too_big = items
+        .iter()
+        .filter(|(_entity, components)| components[0].borrow().downcast_ref::<Size>().unwrap().0 > 10.0)
+        .map(|(entity, _components)| *entity)
+        .collect::<Vec<_>>();
+    for entity in &too_big {
+        world.delete_entity(*entity)?;
+    }
+
+    assert_eq!(too_big.len(), 1);
+    assert!(!world.is_alive(too_big[0]));
+    let items = world.query()
+        .with_component::<Location>()?
+        .run_entities();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].0.index(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn sorted_and_grouped_queries() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+
+    for (x, size) in [(3.0, 1.0), (1.0, 2.0), (2.0, 1.0)] {
+        world.create_entity()
+            .with_component(Location(x, 0.0))?
+            .with_component(Size(size))?;
+    }
+
+    let mut query = world.query();
+    query.with_component::<Location>()?.with_component::<Size>()?;
+
+    let by_x = query.sort_by::<Location>(|a, b| a.0.total_cmp(&b.0))?;
+    let order = by_x.iter().map(|(entity, _)| entity.index()).collect::<Vec<_>>();
+    assert_eq!(order, vec![1, 2, 0]);
+
+    let by_size = query.group_by::<Size, _>(|size| size.0 as u32)?;
+    assert_eq!(by_size.len(), 2);
+    assert_eq!(by_size[0].0, 1);
+    assert_eq!(by_size[0].1.len(), 2);
+
+    Ok(())
+}
+
+struct Follow(Entity);
+
+impl EntityReference for Follow {
+    fn target(&self) -> Entity {
+        self.0
+    }
+}
+
+#[test]
+fn join_through_entity_references() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Follow>();
+
+    world.create_entity().with_component(Location(5.0, 6.0))?;
+    let leader = world.entity_handle(0).unwrap();
+    world.create_entity().with_component(Follow(leader))?;
+
+    let mut query = world.query();
+    query.with_component::<Follow>()?;
+    let joined = query.inner_join::<Follow, Location>()?;
+
+    assert_eq!(joined.len(), 1);
+    assert_eq!(joined[0].0.index(), 1);
+    assert_eq!(joined[0].2.borrow().downcast_ref::<Location>().unwrap().0, 5.0);
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use ecs_library::World;
+
+
+
+#[test]
+fn deleting_a_parent_despawns_the_subtree() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Size>();
+    for index in 0..4 {
+        world.create_entity()
+            .with_component(Size(index as f32))?;
+    }
+    world.add_child(0, 1)?;
+    world.add_child(1, 2)?;
+
+    world.delete_entity_by_id(0)?;
+
+    let query = world.query()
+        .with_component::<Size>()?
+        .run();
+    assert_eq!(query.0, vec![3]);
+    let borrowed_size = query.1[0][0].borrow();
+    let size = borrowed_size.downcast_ref::<Size>().unwrap();
+    assert_eq!(size.0, 3.0);
+
+    Ok(())
+}
+
+#[test]
+fn traverse_scene_graph() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Size>();
+    for index in 0..5 {
+        world.create_entity()
+            .with_component(Size(index as f32))?;
+    }
+    world.add_child(0, 1)?;
+    world.add_child(0, 2)?;
+    world.add_child(2, 3)?;
+    world.set_parent(4, 3)?;
+
+    assert_eq!(world.ancestors(4).collect::<Vec<_>>(), vec![3, 2, 0]);
+    assert_eq!(world.descendants(0).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    assert_eq!(world.depth_first(2).collect::<Vec<_>>(), vec![2, 3, 4]);
+
+    world.remove_child(0, 2)?;
+    assert_eq!(world.parent(2), None);
+    assert_eq!(world.children(0), vec![1]);
+    assert_eq!(world.ancestors(4).collect::<Vec<_>>(), vec![3, 2]);
+
+    Ok(())
+}
+
+struct Size(pub f32);
@@ -0,0 +1,48 @@
+use ecs_library::{Error, World};
+
+
+
+#[test]
+fn lookup_components_by_value() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<NetworkId>();
+    world.register_component::<Team>();
+    world.add_hash_index::<Team>()?;
+    world.add_ordered_index::<NetworkId>()?;
+
+    for (id, team) in [(42, Team::Red), (7, Team::Blue), (13, Team::Red)] {
+        world.create_entity()
+            .with_component(NetworkId(id))?
+            .with_component(team)?;
+    }
+
+    let red = world.lookup(&Team::Red)?;
+    assert_eq!(red.iter().map(|entity| entity.index()).collect::<Vec<_>>(), vec![0, 2]);
+    assert_eq!(world.lookup(&NetworkId(42))?[0].index(), 0);
+
+    *world.get_mut::<Team>(0).unwrap() = Team::Blue;
+    world.delete_entity_by_id(1)?;
+    let blue = world.lookup(&Team::Blue)?;
+    assert_eq!(blue.iter().map(|entity| entity.index()).collect::<Vec<_>>(), vec![0]);
+    assert_eq!(world.lookup_range(NetworkId(10)..NetworkId(50))?.len(), 2);
+
+    let (_indexes, components) = world.query()
+        .with_component::<Team>()?
+        .run();
+    *components[0][0].borrow_mut().downcast_mut::<Team>().unwrap() = Team::Red;
+    world.reindex::<Team>()?;
+    assert_eq!(world.lookup(&Team::Red)?.len(), 2);
+
+    assert!(matches!(world.lookup(&1_u32), Err(Error::MissingIndex(_))));
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct NetworkId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Team {
+    Red,
+    Blue,
+}
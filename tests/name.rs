@@ -0,0 +1,47 @@
+use ecs_library::{Name, World};
+
+
+
+#[test]
+fn find_entities_by_name() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Size>();
+    world.register_component::<Name>();
+
+    world.create_entity()
+        .with_component(Size(1.0))?;
+    world.create_entity()
+        .with_component(Name::new("door_01"))?
+        .with_component(Size(2.0))?;
+    world.set_name(0, "lamp")?;
+
+    let door = world.entity_by_name("door_01").unwrap();
+    assert_eq!(world.get::<Size>(door.index()).unwrap().0, 2.0);
+    assert_eq!(&*world.name(0).unwrap(), "lamp");
+    assert!(world.entity_by_name("window").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn debug_output_shows_names() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Size>();
+    world.register_component::<Name>();
+    world.add_resouce(Size(3.0));
+    world.create_entity().with_component(Size(1.0))?;
+    world.create_entity().with_component(Size(2.0))?;
+    world.create_entity().with_component(Name::new("lamp"))?;
+    world.set_name(1, "door_01")?;
+
+    let output = format!("{world:?}");
+    assert!(output.starts_with("World { entities: {#0: {"), "{output}");
+    assert!(output.contains("door_01: {"), "{output}");
+    assert!(output.contains("Name(\"door_01\")"), "{output}");
+    assert!(output.contains("Name(\"lamp\")"), "{output}");
+    assert!(output.contains("resources: Resource"), "{output}");
+
+    Ok(())
+}
+
+struct Size(pub f32);
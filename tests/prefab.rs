@@ -0,0 +1,32 @@
+use ecs_library::{Prefab, World};
+
+
+
+#[test]
+fn clone_entities_and_spawn_prefabs() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_clone::<Size>();
+    let spawned = world.spawn_prefab(&Prefab::new().with(Size(1.0)))?;
+    world.set_name(spawned.index(), "crate")?;
+    let original = world.entity_by_name("crate").unwrap();
+
+    let clone = world.clone_entity(original)?;
+    assert_eq!(world.get::<Size>(clone.index()).unwrap().0, 1.0);
+    assert_eq!(&*world.name(clone.index()).unwrap(), "crate");
+
+    let stack = Prefab::new()
+        .with(Size(2.0))
+        .with_child(world.prefab_of(original)?)
+        .with_child(world.prefab_of(clone)?);
+    let spawned = world.spawn_prefab_with(&stack, &Prefab::new().with(Size(3.0)))?;
+
+    assert_eq!(world.get::<Size>(spawned.index()).unwrap().0, 3.0);
+    assert_eq!(world.children(spawned.index()).len(), 2);
+    assert!(world.delete_entity(original).is_ok());
+    assert!(world.clone_entity(original).is_err());
+
+    Ok(())
+}
+
+#[derive(Clone)]
+struct Size(pub f32);
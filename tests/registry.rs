@@ -0,0 +1,104 @@
+use std::any::Any;
+
+use ecs_library::{Error, Prefab, Reflect, World};
+
+
+#[test]
+fn debug_entity_lists_component_values() -> anyhow::Result<()> {
+    let mut world = initialize_world();
+    world.create_entity()
+        .with_component(Location { x: 1.0, y: 2.0 })?
+        .with_component(Size(3.0))?;
+
+    let components = world.debug_entity(0)?;
+    let size_name = std::any::type_name::<Size>();
+    let location_name = std::any::type_name::<Location>();
+
+    assert_eq!(components.len(), 2);
+    assert!(components.contains(&(location_name, "Location { x: 1.0, y: 2.0 }".to_string())));
+    assert!(components.contains(&(size_name, format!("{size_name} (4 bytes)"))));
+    Ok(())
+}
+
+#[test]
+fn edit_component_through_reflection() -> anyhow::Result<()> {
+    let mut world = initialize_world();
+    world.create_entity()
+        .with_component(Location { x: 1.0, y: 2.0 })?
+        .with_component(Size(3.0))?;
+
+    let location_name = std::any::type_name::<Location>();
+    world.reflect_component_mut(0, location_name, |location| {
+        *location.field_mut("x").unwrap().downcast_mut::<f32>().unwrap() = 10.0;
+    })?;
+
+    let query = world.query().with_component::<Location>()?.run();
+    let borrowed_location = query.1[0][0].borrow();
+    let location = borrowed_location.downcast_ref::<Location>().unwrap();
+    assert_eq!(location.x, 10.0);
+
+    let query = world.query().with_component::<Size>()?.run();
+    {
+        let borrowed_size = query.1[0][0].borrow();
+        assert_eq!(borrowed_size.downcast_ref::<Size>().unwrap().0, 3.0);
+    }
+
+    let size_name = std::any::type_name::<Size>();
+    let result = world.reflect_component_mut(0, size_name, |_size| ());
+    assert!(matches!(result, Err(Error::ComponentNotReflectable(_))));
+    Ok(())
+}
+
+#[test]
+fn debug_entity_lists_components_from_prefabs_and_copies() -> anyhow::Result<()> {
+    let mut world = World::new();
+    let spawned = world.spawn_prefab(&Prefab::new().with(Size(3.0)))?;
+    let size_name = std::any::type_name::<Size>();
+    let expected = vec![(size_name, format!("{size_name} (4 bytes)"))];
+    assert_eq!(world.debug_entity(spawned.index())?, expected);
+
+    let mut other = World::new();
+    let map = world.copy_entities_to(&[spawned], &mut other)?;
+    let copy = map.get_entity(spawned).unwrap();
+    assert_eq!(other.debug_entity(copy.index())?, expected);
+    Ok(())
+}
+
+fn initialize_world() -> World {
+    let mut world = World::new();
+    world.register_component::<Location>();
+    world.register_component::<Size>();
+    world.type_registry_mut().register_debug::<Location>();
+    world.type_registry_mut().register_reflect::<Location>();
+    world
+}
+
+#[derive(Debug)]
+struct Location {
+    x: f32,
+    y: f32,
+}
+#[derive(Clone)]
+struct Size(pub f32);
+
+impl Reflect for Location {
+    fn field_names(&self) -> &'static [&'static str] {
+        &["x", "y"]
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Any> {
+        match name {
+            "x" => Some(&self.x),
+            "y" => Some(&self.y),
+            _ => None,
+        }
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Any> {
+        match name {
+            "x" => Some(&mut self.x),
+            "y" => Some(&mut self.y),
+            _ => None,
+        }
+    }
+}
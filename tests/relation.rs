@@ -0,0 +1,57 @@
+use ecs_library::{Relation, World};
+
+
+
+#[test]
+fn query_entities_by_relation() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Size>();
+    for index in 0..3 {
+        world.create_entity()
+            .with_component(Size(index as f32))?;
+    }
+    world.add_relation::<ChildOf>(1, 0)?;
+    world.add_relation::<ChildOf>(2, 0)?;
+    world.add_relation::<Likes>(1, 2)?;
+
+    assert_eq!(world.entities_with_relation::<ChildOf>(0), vec![1, 2]);
+    assert_eq!(world.relation_targets::<Likes>(1), vec![2]);
+
+    let query = world.query()
+        .with_component::<Size>()?
+        .with_component::<Relation<Likes>>()?
+        .run();
+    assert_eq!(query.0, vec![1]);
+    let borrowed_size = query.1[0][0].borrow();
+    assert_eq!(borrowed_size.downcast_ref::<Size>().unwrap().0, 1.0);
+
+    Ok(())
+}
+
+#[test]
+fn relations_are_cleaned_up_when_target_is_deleted() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_component::<Size>();
+    for index in 0..3 {
+        world.create_entity()
+            .with_component(Size(index as f32))?;
+    }
+    world.add_relation::<Likes>(0, 2)?;
+    world.add_relation::<Likes>(1, 2)?;
+    world.add_relation::<Likes>(1, 0)?;
+
+    world.delete_entity_by_id(2)?;
+
+    assert!(!world.has_relation::<Likes>(0, 2));
+    assert_eq!(world.relation_targets::<Likes>(1), vec![0]);
+    let query = world.query()
+        .with_component::<Relation<Likes>>()?
+        .run();
+    assert_eq!(query.0, vec![1]);
+
+    Ok(())
+}
+
+struct ChildOf;
+struct Likes;
+struct Size(pub f32);
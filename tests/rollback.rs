@@ -0,0 +1,116 @@
+use ecs_library::{Entity, World};
+
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Health(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Seed(u64);
+
+fn initialize_world() -> anyhow::Result<World> {
+    let mut world = World::new();
+    world.register_clone::<Health>();
+    world.register_binary_component::<u32>();
+    world.register_clone::<u32>();
+    world.register_snapshot_resource::<Seed>();
+    world.add_resouce(Seed(7));
+    for value in 0..8_u32 {
+        world.create_entity().with_component(Health(10))?.with_component(value)?;
+    }
+    Ok(world)
+}
+
+/// One deterministic frame that spawns, despawns and mutates depending on the seed.
+fn step(world: &mut World) -> anyhow::Result<()> {
+    let seed = {
+        let seed = world.get_resource_mut::<Seed>().unwrap();
+        seed.0 = seed.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        seed.0
+    };
+    let alive = alive(world)?;
+    let target = alive[(seed >> 33) as usize % alive.len()];
+    if seed % 3 == 0 && alive.len() > 2 {
+        world.delete_entity_by_id(target)?;
+    } else if seed % 3 == 1 {
+        world.create_entity().with_component(Health(5))?.with_component((seed >> 40) as u32)?;
+    } else {
+        *world.get_mut::<u32>(target).unwrap() += 1;
+        if let Some(mut health) = world.get_mut::<Health>(target) {
+            health.0 = health.0.saturating_sub(1);
+        }
+    }
+    Ok(())
+}
+
+fn alive(world: &World) -> anyhow::Result<Vec<usize>> {
+    Ok(world.query().with_component::<u32>()?.run().0)
+}
+
+/// Snapshot bytes, handles, health and seed.
+type State = (Vec<u8>, Vec<Entity>, Vec<u32>, u64);
+
+fn state(world: &World) -> anyhow::Result<State> {
+    let alive = alive(world)?;
+    let handles = alive.iter().map(|index| world.entity_handle(*index).unwrap()).collect();
+    let health = alive.iter().filter_map(|index| world.get::<Health>(*index).map(|health| health.0)).collect();
+    Ok((world.save_snapshot(), handles, health, world.get_resource::<Seed>().unwrap().0))
+}
+
+#[test]
+fn replaying_after_restore_is_deterministic() -> anyhow::Result<()> {
+    let mut world = initialize_world()?;
+    for _ in 0..20 {
+        step(&mut world)?;
+    }
+    let snapshot = world.snapshot()?;
+    let saved = state(&world)?;
+
+    let mut expected = None;
+    for _ in 0..50 {
+        world.restore(&snapshot)?;
+        assert_eq!(state(&world)?, saved);
+        for _ in 0..30 {
+            step(&mut world)?;
+        }
+        let replayed = state(&world)?;
+        assert_eq!(expected.get_or_insert_with(|| replayed.clone()), &replayed);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn stale_handles_stay_stale_after_restore() -> anyhow::Result<()> {
+    let mut world = initialize_world()?;
+    let deleted = world.entity_handle(3).unwrap();
+    world.delete_entity(deleted)?;
+    let snapshot = world.snapshot()?;
+
+    world.create_entity().with_component(Health(1))?;
+    world.restore(&snapshot)?;
+    assert!(!world.is_alive(deleted));
+
+    let reused = world.create_entity().with_component(Health(2))?.current_entity().unwrap();
+    assert_eq!(reused.index(), 3);
+    assert_ne!(reused, deleted);
+    assert!(!world.is_alive(deleted));
+
+    Ok(())
+}
+
+#[test]
+fn resources_missing_from_the_snapshot_are_removed() -> anyhow::Result<()> {
+    let mut world = World::new();
+    world.register_snapshot_resource::<Seed>();
+    let snapshot = world.snapshot()?;
+
+    world.add_resouce(Seed(1));
+    world.add_resouce(2_u32);
+    world.restore(&snapshot)?;
+
+    assert!(world.get_resource::<Seed>().is_none());
+    assert_eq!(*world.get_resource::<u32>().unwrap(), 2);
+
+    Ok(())
+}
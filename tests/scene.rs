@@ -0,0 +1,82 @@
+use std::str::FromStr;
+
+use ecs_library::scene::SceneLoader;
+use ecs_library::World;
+
+
+
+const LEVEL: &str = "
+# front door with its hinge
+entity door_01
+    Name: door_01
+    Position: 4.0 2.0
+    Health: 10
+
+entity hinge
+    parent: door_01
+    Position: 0.0 1.0
+
+entity lamp
+    Position: -1.0 0.0
+";
+
+#[test]
+fn spawn_and_despawn_scenes() -> anyhow::Result<()> {
+    let scene = loader().parse(LEVEL)?;
+    let mut world = World::new();
+    world.register_component::<Health>();
+    world.create_entity().with_component(Health(99))?;
+
+    let first = world.spawn_scene(&scene)?;
+    let second = world.spawn_scene(&scene)?;
+
+    let door = first.get("door_01").unwrap();
+    let hinge = first.get("hinge").unwrap();
+    assert_eq!(world.entity_by_name("door_01"), Some(door));
+    assert_eq!(world.children(door.index()), vec![hinge.index()]);
+    let position = world.get::<Position>(hinge.index()).unwrap();
+    assert_eq!((position.0, position.1), (0.0, 1.0));
+    drop(position);
+    assert_eq!(world.get::<Health>(door.index()).unwrap().0, 10);
+    assert_ne!(second.get("door_01"), Some(door));
+
+    assert_eq!(world.despawn_scene(&first)?, 3);
+    assert!(!world.is_alive(hinge));
+    assert_eq!(world.query().with_component::<Position>()?.count(), 3);
+    assert_eq!(world.query().with_component::<Health>()?.count(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn load_scene_files() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!("ecs_library_scene_{}.txt", std::process::id()));
+    std::fs::write(&path, LEVEL)?;
+    let scene = loader().load(&path);
+    std::fs::remove_file(&path)?;
+
+    assert_eq!(scene?.len(), 3);
+    assert!(loader().load(&path).is_err());
+
+    Ok(())
+}
+
+fn loader() -> SceneLoader {
+    let mut loader = SceneLoader::new();
+    loader
+        .register::<Position>("Position")
+        .register_with("Health", |text| text.parse().ok().map(Health));
+    loader
+}
+
+struct Health(u32);
+struct Position(f32, f32);
+
+impl FromStr for Position {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (x, y) = text.split_once(' ').ok_or(())?;
+        Ok(Position(x.parse().map_err(|_| ())?, y.parse().map_err(|_| ())?))
+    }
+}
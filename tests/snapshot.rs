@@ -0,0 +1,84 @@
+use ecs_library::{BinaryComponent, Error, World};
+
+
+#[test]
+fn snapshot_restores_entities_in_a_new_world() -> anyhow::Result<()> {
+    let mut world = initialize_world();
+    world.create_entity()
+        .with_component(Location(10.0, 11.0))?
+        .with_component(Size(1.0))?;
+    world.create_entity()
+        .with_component(Size(2.0))?;
+
+    let snapshot = world.save_snapshot();
+
+    let mut loaded = initialize_world();
+    loaded.load_snapshot(&snapshot)?;
+
+    let query = loaded.query()
+        .with_component::<Location>()?
+        .with_component::<Size>()?
+        .run();
+    assert_eq!(query.0, vec![0]);
+
+    let borrowed_location = query.1[0][0].borrow();
+    let location = borrowed_location.downcast_ref::<Location>().unwrap();
+    assert_eq!(location.0, 10.0);
+    assert_eq!(location.1, 11.0);
+
+    let query = loaded.query()
+        .with_component::<Size>()?
+        .run();
+    assert_eq!(query.0, vec![0, 1]);
+
+    Ok(())
+}
+
+#[test]
+fn loading_fails_for_unregistered_components() -> anyhow::Result<()> {
+    let mut world = initialize_world();
+    world.create_entity()
+        .with_component(Location(10.0, 11.0))?;
+    let snapshot = world.save_snapshot();
+
+    let mut loaded = World::new();
+    loaded.register_binary_component::<Size>();
+    let result = loaded.load_snapshot(&snapshot);
+
+    assert!(matches!(result, Err(Error::UnknownSnapshotComponent(_))));
+    Ok(())
+}
+
+fn initialize_world() -> World {
+    let mut world = World::new();
+    world.register_binary_component::<Location>();
+    world.register_binary_component::<Size>();
+    world
+}
+
+struct Location(pub f32, pub f32);
+struct Size(pub f32);
+
+impl BinaryComponent for Location {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+        self.1.encode(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 8 {
+            return None;
+        }
+        Some(Location(f32::decode(&bytes[..4])?, f32::decode(&bytes[4..])?))
+    }
+}
+
+impl BinaryComponent for Size {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        self.0.encode(buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(Size(f32::decode(bytes)?))
+    }
+}
@@ -0,0 +1,67 @@
+use ecs_library::spatial::SpatialGrid;
+use ecs_library::transform::Transform;
+use ecs_library::World;
+
+
+
+#[test]
+fn radius_and_box_queries() -> anyhow::Result<()> {
+    let mut world = initialize_world()?;
+    let mut grid = SpatialGrid::<Transform>::new(5.0)?;
+    assert_eq!(grid.update(&world), 4);
+
+    assert_eq!(indexes(&grid.within_radius((0.0, 0.0), 5.0)), vec![0, 1]);
+    assert_eq!(indexes(&grid.within_radius((0.0, 0.0), 15.0)), vec![0, 1, 2]);
+    assert_eq!(indexes(&grid.within_aabb((-1.0, -1.0), (20.0, 2.0))), vec![0, 2]);
+    assert_eq!(indexes(&grid.within_aabb((-100.0, -100.0), (0.0, 0.0))), vec![0, 3]);
+
+    world.delete_entity_by_id(3)?;
+    assert_eq!(grid.update(&world), 1);
+    assert_eq!(grid.len(), 3);
+    assert_eq!(grid.update(&world), 0);
+
+    Ok(())
+}
+
+#[test]
+fn moved_entities_are_rebucketed() -> anyhow::Result<()> {
+    let mut world = initialize_world()?;
+    let mut grid = SpatialGrid::<Transform>::new(5.0)?;
+    grid.update(&world);
+
+    world.get_mut::<Transform>(2).unwrap().translation = (1.0, 1.0);
+    assert_eq!(grid.update(&world), 1);
+    assert_eq!(indexes(&grid.within_radius((0.0, 0.0), 2.0)), vec![0, 2]);
+
+    world.create_entity().with_component(Transform::from_translation(-1.0, 0.0))?;
+    grid.update(&world);
+    assert_eq!(indexes(&grid.within_radius((0.0, 0.0), 2.0)), vec![0, 2, 4]);
+
+    Ok(())
+}
+
+#[test]
+fn huge_queries_only_visit_occupied_cells() -> anyhow::Result<()> {
+    let world = initialize_world()?;
+    let mut grid = SpatialGrid::<Transform>::new(5.0)?;
+    grid.update(&world);
+
+    assert_eq!(indexes(&grid.within_aabb((-1e30, -1e30), (1e30, 1e30))), vec![0, 1, 2, 3]);
+    assert_eq!(indexes(&grid.within_radius((0.0, 0.0), 1e30)), vec![0, 1, 2, 3]);
+
+    Ok(())
+}
+
+fn indexes(entities: &[ecs_library::Entity]) -> Vec<usize> {
+    entities.iter().map(|entity| entity.index()).collect()
+}
+
+fn initialize_world() -> anyhow::Result<World> {
+    let mut world = World::new();
+    world.register_component::<Transform>();
+    for (x, y) in [(0.0, 0.0), (3.0, 3.0), (12.0, 0.0), (-50.0, -50.0)] {
+        world.create_entity()
+            .with_component(Transform::from_translation(x, y))?;
+    }
+    Ok(world)
+}
@@ -0,0 +1,102 @@
+use ecs_library::transform::{register_transform_components, TransformParent};
+use ecs_library::{Error, Relation, World};
+
+
+
+#[test]
+fn move_entities_between_worlds() -> anyhow::Result<()> {
+    let mut simulation = initialize_world()?;
+    let mut ui = World::new();
+    ui.register_component::<Label>();
+    ui.create_entity().with_component(Label("score"))?;
+
+    let root = simulation.entity_handle(0).unwrap();
+    let map = simulation.move_entities_to(&[root], &mut ui)?;
+
+    let moved_root = map.get_entity(root).unwrap();
+    assert_eq!(moved_root.index(), 1);
+    let children = ui.children(moved_root.index());
+    assert_eq!(children.len(), 1);
+    assert_eq!(ui.get::<Size>(children[0]).unwrap().0, 2.0);
+    assert_eq!(ui.relation_targets::<Likes>(children[0]), vec![moved_root.index()]);
+
+    assert!(!simulation.is_alive(root));
+    assert!(simulation.entity_handle(1).is_none());
+    assert!(simulation.entity_handle(2).is_some());
+
+    Ok(())
+}
+
+#[test]
+fn copies_need_clone_functions() -> anyhow::Result<()> {
+    let simulation = initialize_world()?;
+    let mut prediction = World::new();
+
+    let uncloneable = simulation.entity_handle(2).unwrap();
+    let copied = simulation.copy_entities_to(&[uncloneable], &mut prediction);
+    assert!(matches!(copied, Err(Error::ComponentNotCloneable(_))));
+
+    let root = simulation.entity_handle(0).unwrap();
+    let map = simulation.copy_entities_to(&[root], &mut prediction)?;
+    assert_eq!(map.len(), 2);
+    assert!(simulation.is_alive(root));
+
+    Ok(())
+}
+
+#[test]
+fn merge_worlds() -> anyhow::Result<()> {
+    let mut simulation = initialize_world()?;
+    simulation.add_resouce(1_u32);
+    let mut other = initialize_world()?;
+    other.add_resouce(2_u32);
+    other.add_resouce(3.0_f64);
+
+    let map = simulation.merge(other)?;
+
+    assert_eq!(map.len(), 3);
+    assert_eq!(simulation.query().with_component::<Size>()?.count(), 6);
+    assert_eq!(*simulation.get_resource::<u32>().unwrap(), 1);
+    assert_eq!(*simulation.get_resource::<f64>().unwrap(), 3.0);
+
+    Ok(())
+}
+
+#[test]
+fn links_to_entities_left_behind_are_dropped() -> anyhow::Result<()> {
+    let mut simulation = initialize_world()?;
+    register_transform_components(&mut simulation);
+    simulation.add_component_to_entity_by_id(TransformParent(0), 2)?;
+    simulation.add_relation::<Likes>(2, 0)?;
+    let mut prediction = World::new();
+
+    let copied = simulation.entity_handle(2).unwrap();
+    simulation.delete_component_by_entity_id::<Label>(2)?;
+    let map = simulation.copy_entities_to(&[copied], &mut prediction)?;
+
+    let copy = map.get_entity(copied).unwrap().index();
+    assert!(prediction.get::<TransformParent>(copy).is_none());
+    assert!(!prediction.has::<Relation<Likes>>(copy));
+    assert_eq!(prediction.get::<Size>(copy).unwrap().0, 3.0);
+
+    Ok(())
+}
+
+fn initialize_world() -> anyhow::Result<World> {
+    let mut world = World::new();
+    world.register_clone::<Size>();
+    world.register_component::<Label>();
+    world.create_entity().with_component(Size(1.0))?;
+    world.create_entity().with_component(Size(2.0))?;
+    world.create_entity()
+        .with_component(Size(3.0))?
+        .with_component(Label("not cloneable"))?;
+    world.add_child(0, 1)?;
+    world.add_relation::<Likes>(1, 0)?;
+    Ok(world)
+}
+
+#[derive(Clone)]
+struct Size(pub f32);
+struct Label(#[allow(dead_code)] &'static str);
+struct Likes;
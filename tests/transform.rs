@@ -0,0 +1,85 @@
+use ecs_library::transform::{
+    propagate_transforms, register_transform_components, GlobalTransform, Transform, TransformParent,
+};
+use ecs_library::{Error, World};
+
+
+
+#[test]
+fn globals_follow_parent_links() -> anyhow::Result<()> {
+    let world = initialize_world()?;
+
+    assert_eq!(propagate_transforms(&world)?, 3);
+    assert_eq!(global_translation(&world, 2)?, (11.0, 1.0));
+
+    Ok(())
+}
+
+#[test]
+fn only_changed_subtrees_are_updated() -> anyhow::Result<()> {
+    let mut world = initialize_world()?;
+    propagate_transforms(&world)?;
+    assert_eq!(propagate_transforms(&world)?, 0);
+
+    {
+        let query = world.query()
+            .with_component::<Transform>()?
+            .run();
+        let row = query.0.iter().position(|index| *index == 1).unwrap();
+        let mut borrowed_transform = query.1[0][row].borrow_mut();
+        let transform = borrowed_transform.downcast_mut::<Transform>().unwrap();
+        transform.translation.0 = 5.0;
+    }
+
+    assert_eq!(propagate_transforms(&world)?, 2);
+    assert_eq!(global_translation(&world, 0)?, (1.0, 0.0));
+    assert_eq!(global_translation(&world, 2)?, (6.0, 1.0));
+
+    world.delete_component_by_entity_id::<TransformParent>(2)?;
+    assert_eq!(propagate_transforms(&world)?, 1);
+    assert_eq!(global_translation(&world, 2)?, (0.0, 1.0));
+
+    Ok(())
+}
+
+#[test]
+fn parent_cycles_are_rejected() -> anyhow::Result<()> {
+    let mut world = initialize_world()?;
+    world.add_component_to_entity_by_id(TransformParent(2), 0)?;
+
+    assert!(matches!(propagate_transforms(&world), Err(Error::InvalidHierarchy(_))));
+    assert_eq!(global_translation(&world, 0)?, (0.0, 0.0));
+
+    world.add_component_to_entity_by_id(TransformParent(1), 1)?;
+    world.delete_component_by_entity_id::<TransformParent>(0)?;
+    assert!(matches!(propagate_transforms(&world), Err(Error::InvalidHierarchy(_))));
+
+    Ok(())
+}
+
+// 0 at (1, 0) -> 1 at (10, 0) -> 2 at (0, 1)
+fn initialize_world() -> anyhow::Result<World> {
+    let mut world = World::new();
+    register_transform_components(&mut world);
+    world.create_entity()
+        .with_component(Transform::from_translation(1.0, 0.0))?
+        .with_component(GlobalTransform::default())?;
+    world.create_entity()
+        .with_component(Transform::from_translation(10.0, 0.0))?
+        .with_component(GlobalTransform::default())?
+        .with_component(TransformParent(0))?;
+    world.create_entity()
+        .with_component(Transform::from_translation(0.0, 1.0))?
+        .with_component(GlobalTransform::default())?
+        .with_component(TransformParent(1))?;
+    Ok(world)
+}
+
+fn global_translation(world: &World, index: usize) -> anyhow::Result<(f32, f32)> {
+    let query = world.query()
+        .with_component::<GlobalTransform>()?
+        .run();
+    let row = query.0.iter().position(|queried| *queried == index).unwrap();
+    let borrowed_global = query.1[0][row].borrow();
+    Ok(borrowed_global.downcast_ref::<GlobalTransform>().unwrap().get().translation)
+}